@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+use crate::tree;
+
+// What a definition turns into, given the text it matched and what the
+// definitions inside it turned into
+pub type Action<T> = Box<dyn Fn(&str, Vec<T>) -> T + Send + Sync>;
+
+// The action of each definition that has one
+pub struct Actions<T> {
+    actions: HashMap<String, Action<T>>
+}
+
+impl<T> Default for Actions<T> {
+    fn default() -> Actions<T> {
+        return Actions::new()
+    }
+}
+
+impl<T> Actions<T> {
+    pub fn new() -> Actions<T> {
+        return Actions{actions: HashMap::new()}
+    }
+
+    // Replaces whatever the rule had before
+    pub fn on<F>(&mut self, rule: &str, action: F) -> &mut Actions<T> where F: Fn(&str, Vec<T>) -> T + Send + Sync + 'static {
+        self.actions.insert(rule.to_string(), Box::new(action));
+        return self
+    }
+
+    // Works from the leaves up, a definition without an action hands what its
+    // children turned into straight to its parent. `input` is what the tree was
+    // matched against
+    pub fn evaluate(&self, tree: &tree::Tree, input: &str) -> Vec<T> {
+        let mut values: Vec<Vec<T>> = vec![Vec::new()];
+        let mut pending: Vec<(&tree::Tree, bool)> = vec![(tree, false)];
+        while let Some((tree, visited)) = pending.pop() {
+            if !visited {
+                pending.push((tree, true));
+                pending.extend(tree.children.iter().rev().map(|child| (child, false)));
+                values.push(Vec::new());
+                continue
+            };
+            let children = values.pop().unwrap_or_default();
+            let value = match self.actions.get(&tree.rule) {
+                Some(action) => vec![action(&input[tree.start..tree.end], children)],
+                None => children
+            };
+            if let Some(parent) = values.last_mut() {
+                parent.extend(value);
+            }
+        };
+        return values.pop().unwrap_or_default()
+    }
+}
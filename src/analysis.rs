@@ -0,0 +1,604 @@
+use std::collections::{HashMap, HashSet, BTreeSet};
+use crate::nodes;
+use crate::context;
+use crate::prelude;
+use crate::caseFolding;
+use crate::formatter;
+use crate::tokenTypes::TokenTypes::*;
+
+pub type Ranges = Vec<(u32, u32)>;
+
+// How many rules the calls to one rule with parameters can turn into before
+// `classify` stops looking
+const MAX_INSTANCES: usize = 100;
+// The same for rules that call themselves with bigger arguments, which can
+// double in size every time
+const GROWING_INSTANCES: usize = 8;
+
+// What a node can start with: whether it can succeed without consuming anything,
+// and the characters a match that does consume something can begin with. Both
+// are allowed to say too much but never too little
+#[derive(Clone, PartialEq, Debug)]
+pub struct First {
+    pub nullable: bool,
+    pub chars: Ranges
+}
+
+impl First {
+    fn never() -> First {
+        return First{nullable: false, chars: Vec::new()}
+    }
+
+    fn anything() -> First {
+        return First{nullable: true, chars: vec![(0, char::MAX as u32)]}
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        let c = c as u32;
+        return self.chars.iter().any(|(lo, hi)| *lo <= c && c <= *hi)
+    }
+}
+
+// FIRST sets of every parameterless definition. Definitions can use each other in
+// circles, so they're all computed together, starting from nothing and growing
+// until nothing changes anymore
+pub struct Analysis<'a> {
+    context: &'a context::Context,
+    rules: HashMap<String, First>
+}
+
+pub fn analyse(context: &context::Context) -> Analysis<'_> {
+    let mut analysis = Analysis{context, rules: HashMap::new()};
+    for (name, lazy) in context.symbols.iter() {
+        if lazy.params.is_empty() {
+            analysis.rules.insert(name.clone(), First::never());
+        }
+    };
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (name, lazy) in context.symbols.iter() {
+            if !lazy.params.is_empty() {
+                continue
+            };
+            let first = analysis.first(&lazy.fun);
+            if analysis.rules[name] != first {
+                analysis.rules.insert(name.clone(), first);
+                changed = true;
+            }
+        }
+    };
+    return analysis
+}
+
+impl<'a> Analysis<'a> {
+    pub fn first(&self, node: &nodes::Node) -> First {
+        match node {
+            nodes::Node::StrNode{string, caseless, pos_start: _, pos_end: _} => match string.chars().next() {
+                Some(c) => First{nullable: false, chars: char_set(c, *caseless)},
+                None => First{nullable: true, chars: Vec::new()}
+            },
+            nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => {
+                let mut res = First{nullable: true, chars: Vec::new()};
+                for rule in chain {
+                    let first = self.first(rule);
+                    res.chars = union(&res.chars, &first.chars);
+                    if !first.nullable {
+                        res.nullable = false;
+                        break
+                    }
+                };
+                res
+            },
+            nodes::Node::BinOpNode{left, op: Pipe, right, pos_start: _, pos_end: _} => {
+                let (left, right) = (self.first(left), self.first(right));
+                First{nullable: left.nullable || right.nullable, chars: union(&left.chars, &right.chars)}
+            },
+            nodes::Node::PostFixNode{postfix, op, pos_start: _, pos_end: _} => {
+                let inner = self.first(postfix);
+                First{nullable: inner.nullable || *op != Plus, chars: inner.chars}
+            },
+            nodes::Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => match self.context.symbols.get(identifier) {
+                // Used without its arguments, which always fails
+                Some(lazy) if !lazy.params.is_empty() => First::never(),
+                Some(_) => self.rules.get(identifier).cloned().unwrap_or_else(First::anything),
+                None => match prelude::lookup(identifier) {
+                    Some(prelude::Builtin::EndOfInput) => First{nullable: true, chars: Vec::new()},
+                    Some(prelude::Builtin::Newline) => First{nullable: false, chars: vec![('\n' as u32, '\n' as u32), ('\r' as u32, '\r' as u32)]},
+                    Some(prelude::Builtin::Class(_)) => First{nullable: false, chars: prelude::ranges(identifier).unwrap_or(vec![(0, char::MAX as u32)])},
+                    None => First::never()
+                }
+            },
+            // Whatever the arguments turn it into isn't worth working out here
+            _ => First::anything()
+        }
+    }
+}
+
+// What can come right after a match: the characters, and whether the input can
+// end there. Like `First` it can say too much but never too little
+#[derive(Clone, PartialEq, Debug)]
+pub struct Follow {
+    pub chars: Ranges,
+    pub end: bool
+}
+
+impl Follow {
+    fn nothing() -> Follow {
+        return Follow{chars: Vec::new(), end: false}
+    }
+
+    fn anything() -> Follow {
+        return Follow{chars: vec![(0, char::MAX as u32)], end: true}
+    }
+
+    fn union(&self, other: &Follow) -> Follow {
+        return Follow{chars: union(&self.chars, &other.chars), end: self.end || other.end}
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct RuleAnalysis {
+    // Whether it can succeed without consuming anything
+    pub nullable: bool,
+    pub first: Ranges,
+    // The literals a match can start with, a match can also start with a
+    // built-in class instead so `first` is the one to go by for what's possible
+    pub literals: BTreeSet<String>,
+    pub follow: Follow
+}
+
+// Nullable, FIRST and FOLLOW for every parameterless definition. FOLLOW is for
+// matches that start at the `entry` rule, rules that take parameters can be called
+// with anything around them so whatever they use can be followed by anything
+pub struct GrammarAnalysis {
+    pub rules: HashMap<String, RuleAnalysis>
+}
+
+impl GrammarAnalysis {
+    pub fn new(context: &context::Context, entry: &str) -> GrammarAnalysis {
+        let analysis = analyse(context);
+        let literals = first_literals(context, &analysis);
+        let follows = follows(context, &analysis, entry);
+        let mut rules = HashMap::new();
+        for (name, first) in analysis.rules.iter() {
+            let rule = RuleAnalysis{
+                nullable: first.nullable,
+                first: first.chars.clone(),
+                literals: literals.get(name).cloned().unwrap_or_default(),
+                follow: follows.get(name).cloned().unwrap_or_else(Follow::nothing)
+            };
+            rules.insert(name.clone(), rule);
+        };
+        return GrammarAnalysis{rules}
+    }
+
+    pub fn nullable(&self, rule: &str) -> Option<bool> {
+        return self.rules.get(rule).map(|rule| rule.nullable)
+    }
+
+    pub fn first(&self, rule: &str) -> Option<&Ranges> {
+        return self.rules.get(rule).map(|rule| &rule.first)
+    }
+
+    pub fn follow(&self, rule: &str) -> Option<&Follow> {
+        return self.rules.get(rule).map(|rule| &rule.follow)
+    }
+}
+
+// Grows from nothing like FIRST does, the sets only ever get bigger so it stops
+fn first_literals(context: &context::Context, analysis: &Analysis) -> HashMap<String, BTreeSet<String>> {
+    let mut literals: HashMap<String, BTreeSet<String>> = context.symbols.iter().filter(|(_, lazy)| lazy.params.is_empty()).map(|(name, _)| (name.clone(), BTreeSet::new())).collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (name, lazy) in context.symbols.iter() {
+            if !lazy.params.is_empty() {
+                continue
+            };
+            let mut found = BTreeSet::new();
+            starts_with(&lazy.fun, analysis, &literals, &mut found);
+            if literals[name] != found {
+                literals.insert(name.clone(), found);
+                changed = true;
+            }
+        }
+    };
+    return literals
+}
+
+// Adds the literals `node` can start with to `found`, and says whether it's nullable
+fn starts_with(node: &nodes::Node, analysis: &Analysis, literals: &HashMap<String, BTreeSet<String>>, found: &mut BTreeSet<String>) -> bool {
+    match node {
+        nodes::Node::StrNode{string, caseless: _, pos_start: _, pos_end: _} => {
+            if string.is_empty() {
+                return true
+            };
+            found.insert(string.clone());
+            false
+        },
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => chain.iter().all(|rule| starts_with(rule, analysis, literals, found)),
+        nodes::Node::BinOpNode{left, op: _, right, pos_start: _, pos_end: _} => {
+            let left = starts_with(left, analysis, literals, found);
+            let right = starts_with(right, analysis, literals, found);
+            left || right
+        },
+        nodes::Node::PostFixNode{postfix, op, pos_start: _, pos_end: _} => starts_with(postfix, analysis, literals, found) || *op != Plus,
+        nodes::Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => match literals.get(identifier) {
+            Some(theirs) => {
+                found.extend(theirs.iter().cloned());
+                analysis.rules.get(identifier).is_none_or(|first| first.nullable)
+            },
+            None => matches!(prelude::lookup(identifier), Some(prelude::Builtin::EndOfInput))
+        },
+        _ => true
+    }
+}
+
+// FOLLOW of every parameterless definition, grown from nothing but the end of
+// the input after `entry`
+fn follows(context: &context::Context, analysis: &Analysis, entry: &str) -> HashMap<String, Follow> {
+    let mut follows: HashMap<String, Follow> = analysis.rules.keys().map(|name| (name.clone(), Follow::nothing())).collect();
+    if let Some(entry) = follows.get_mut(entry) {
+        entry.end = true;
+    };
+    let mut changed = true;
+    while changed {
+        let mut grown = follows.clone();
+        for (name, lazy) in context.symbols.iter() {
+            let after = match follows.get(name) {
+                Some(follow) if lazy.params.is_empty() => follow.clone(),
+                _ => Follow::anything()
+            };
+            follow(&lazy.fun, &after, &lazy.params, analysis, &mut grown);
+        };
+        changed = grown != follows;
+        follows = grown;
+    };
+    return follows
+}
+
+// Adds what can come after every definition used in `node` to `follows`, when
+// `after` is what can come after `node`
+fn follow(node: &nodes::Node, after: &Follow, params: &Vec<String>, analysis: &Analysis, follows: &mut HashMap<String, Follow>) {
+    match node {
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => {
+            let mut after = after.clone();
+            for rule in chain.iter().rev() {
+                follow(rule, &after, params, analysis, follows);
+                let first = analysis.first(rule);
+                let rest = if first.nullable { after } else { Follow::nothing() };
+                after = rest.union(&Follow{chars: first.chars, end: false});
+            }
+        },
+        nodes::Node::BinOpNode{left, op: _, right, pos_start: _, pos_end: _} => {
+            follow(left, after, params, analysis, follows);
+            follow(right, after, params, analysis, follows);
+        },
+        nodes::Node::PostFixNode{postfix, op, pos_start: _, pos_end: _} => {
+            // A repeated node can be followed by itself
+            let after = if *op == QuestionMark { after.clone() } else { after.union(&Follow{chars: analysis.first(postfix).chars, end: false}) };
+            follow(postfix, &after, params, analysis, follows);
+        },
+        nodes::Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => {
+            if params.contains(identifier) {
+                return
+            };
+            // Inside a rule with parameters, FIRST doesn't know what the
+            // parameters match, so what comes next could be anything
+            let after = if params.is_empty() { after.clone() } else { Follow::anything() };
+            if let Some(follow) = follows.get_mut(identifier) {
+                *follow = follow.union(&after);
+            }
+        },
+        nodes::Node::RuleCallNode{identifier: _, args, pos_start: _, pos_end: _} => args.iter().for_each(|arg| follow(arg, &Follow::anything(), params, analysis, follows)),
+        _ => ()
+    }
+}
+
+// How much it takes to match a rule, each needs everything the ones before it
+// need and more. A rule is the last of these that applies to it or to any rule
+// it ends up using, since matching it means matching those too
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone)]
+pub enum Class {
+    // Nothing it uses ever ends up using itself, so it could be written as a plain regex
+    Regular,
+    // Something it uses uses itself, but only ever after consuming something,
+    // like nesting parenthesis
+    ContextFree,
+    // Something it uses looks at the input without consuming it, which is what `EOI` does
+    UsesPredicates,
+    // Something it uses can get back to itself without consuming anything, and
+    // so never finishes
+    LeftRecursive
+}
+
+pub struct Classification {
+    // Every rule that doesn't take parameters, along with what it uses
+    pub rules: HashMap<String, Class>,
+    // The most any rule needs
+    pub grammar: Class
+}
+
+// Worked out from which rules use which and where. A rule with parameters is
+// looked at once for every set of arguments it's called with, as the rule it
+// turns into with them filled in
+pub fn classify(context: &context::Context) -> Classification {
+    let analysis = analyse(context);
+    let mut edges: HashMap<String, Vec<String>> = HashMap::new();
+    let mut left: HashMap<String, Vec<String>> = HashMap::new();
+    // Calls whose arguments keep growing never run out of rules to turn into,
+    // the ones past the limit are taken to be recursive
+    let mut cut_short = HashSet::new();
+    let mut instances: HashMap<String, usize> = HashMap::new();
+    let growing: HashSet<&String> = growing(context).into_iter().map(|(name, _)| name).collect();
+    let mut pending: Vec<(String, String, nodes::Node)> = context.symbols.iter().filter(|(_, lazy)| lazy.params.is_empty()).map(|(name, lazy)| (name.clone(), name.clone(), lazy.fun.copy())).collect();
+    while let Some((key, rule, body)) = pending.pop() {
+        if edges.contains_key(&key) || cut_short.contains(&key) {
+            continue
+        };
+        let limit = if growing.contains(&rule) { GROWING_INSTANCES } else { MAX_INSTANCES };
+        let count = instances.entry(rule).or_insert(0);
+        if *count >= limit {
+            cut_short.insert(key);
+            continue
+        };
+        *count += 1;
+        let mut used = Vec::new();
+        calls(&body, &mut used);
+        let mut names = Vec::new();
+        for node in used {
+            let (name, instance) = instance(node, context);
+            if let Some(instance) = instance {
+                pending.push((name.clone(), rule_name(node), instance));
+            };
+            names.push(name);
+        };
+        edges.insert(key.clone(), names);
+        let mut used = Vec::new();
+        leftmost(&body, context, &analysis, &growing, &mut Vec::new(), &mut used);
+        left.insert(key, used.into_iter().map(|node| instance(node, context).0).collect());
+    };
+    let reach = |from: &Vec<String>, edges: &HashMap<String, Vec<String>>| -> HashSet<String> {
+        let mut seen = HashSet::new();
+        let mut pending = from.clone();
+        while let Some(name) = pending.pop() {
+            if seen.contains(&name) {
+                continue
+            };
+            if let Some(next) = edges.get(&name) {
+                pending.extend(next.iter().cloned());
+            };
+            seen.insert(name);
+        };
+        return seen
+    };
+    let cyclic: HashSet<&String> = edges.iter().filter(|(name, next)| reach(next, &edges).contains(*name)).map(|(name, _)| name).chain(cut_short.iter()).collect();
+    // Getting to a call that was cut short without consuming anything means
+    // the arguments kept growing all the way there, which never ends either
+    let left_cyclic: HashSet<&String> = left.iter().filter(|(name, next)| {
+        let reached = reach(next, &left);
+        reached.contains(*name) || reached.iter().any(|used| cut_short.contains(used))
+    }).map(|(name, _)| name).collect();
+    // Unless a rule called EOI takes its place
+    let predicate = !context.symbols.contains_key("EOI");
+    let mut rules = HashMap::new();
+    for (name, lazy) in context.symbols.iter() {
+        if !lazy.params.is_empty() {
+            continue
+        };
+        let reached = reach(&vec![name.clone()], &edges);
+        let class = if reached.iter().any(|used| left_cyclic.contains(used)) {
+            Class::LeftRecursive
+        } else if predicate && reached.contains("EOI") {
+            Class::UsesPredicates
+        } else if reached.iter().any(|used| cyclic.contains(used)) {
+            Class::ContextFree
+        } else {
+            Class::Regular
+        };
+        rules.insert(name.clone(), class);
+    };
+    let grammar = rules.values().max().copied().unwrap_or(Class::Regular);
+    return Classification{rules, grammar}
+}
+
+// Rules that end up calling themselves with bigger arguments than they got, like
+// `f(x) := 'a' f((x x)) | x`, along with a call that makes them bigger. Filling
+// in the arguments of those never stops, so there's no writing out a copy of
+// them for every set of arguments they're used with
+pub fn growing(context: &context::Context) -> Vec<(&String, &nodes::Node)> {
+    let mut names: Vec<&String> = context.symbols.keys().collect();
+    names.sort();
+    // Where each parameter gets passed on to, and whether it's passed on as
+    // it is or inside something bigger
+    let mut passes = Vec::new();
+    for name in names {
+        let lazy = &context.symbols[name];
+        passed_on(&lazy.fun, name, &lazy.params, context, &mut passes);
+    };
+    let reaches = |from: (&String, usize), to: (&String, usize)| -> bool {
+        let mut seen = HashSet::new();
+        let mut pending = vec![from];
+        while let Some(at) = pending.pop() {
+            if at == to {
+                return true
+            };
+            if seen.insert(at) {
+                pending.extend(passes.iter().filter(|(param, _, _, _)| *param == at).map(|(_, arg, _, _)| *arg));
+            }
+        };
+        return false
+    };
+    let mut found: Vec<(&String, &nodes::Node)> = Vec::new();
+    for (param, arg, bigger, call) in passes.iter() {
+        if *bigger && !found.iter().any(|(name, _)| *name == param.0) && reaches(*arg, *param) {
+            found.push((param.0, call));
+        }
+    };
+    return found
+}
+
+// A parameter, as which rule and which of its parameters, passed on to another
+// one, whether it's inside something bigger and the call it happens in
+type Pass<'c> = ((&'c String, usize), (&'c String, usize), bool, &'c nodes::Node);
+
+// Every parameter of `rule` used in an argument of a call in `node`, as which
+// parameter of which rule it ends up as, whether the argument is more than just
+// the parameter and the call it's passed to
+fn passed_on<'c>(node: &'c nodes::Node, rule: &'c String, params: &'c [String], context: &'c context::Context, passes: &mut Vec<Pass<'c>>) {
+    match node {
+        nodes::Node::RuleCallNode{identifier, args, pos_start: _, pos_end: _} => {
+            if let Some((called, _)) = context.symbols.get_key_value(identifier).filter(|(_, lazy)| lazy.params.len() == args.len()) {
+                for (j, arg) in args.iter().enumerate() {
+                    for (i, param) in params.iter().enumerate() {
+                        let exactly = matches!(arg.unwrapped(), nodes::Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} if identifier == param);
+                        if exactly || mentions(arg, param) {
+                            passes.push(((rule, i), (called, j), !exactly, node));
+                        }
+                    }
+                }
+            };
+            args.iter().for_each(|arg| passed_on(arg, rule, params, context, passes));
+        },
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => chain.iter().for_each(|item| passed_on(item, rule, params, context, passes)),
+        nodes::Node::BinOpNode{left, op: _, right, pos_start: _, pos_end: _} => {
+            passed_on(left, rule, params, context, passes);
+            passed_on(right, rule, params, context, passes);
+        },
+        nodes::Node::PostFixNode{postfix, op: _, pos_start: _, pos_end: _} => passed_on(postfix, rule, params, context, passes),
+        _ => ()
+    }
+}
+
+fn mentions(node: &nodes::Node, param: &String) -> bool {
+    match node {
+        nodes::Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => identifier == param,
+        nodes::Node::RuleCallNode{identifier: _, args, pos_start: _, pos_end: _} => args.iter().any(|arg| mentions(arg, param)),
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => chain.iter().any(|item| mentions(item, param)),
+        nodes::Node::BinOpNode{left, op: _, right, pos_start: _, pos_end: _} => mentions(left, param) || mentions(right, param),
+        nodes::Node::PostFixNode{postfix, op: _, pos_start: _, pos_end: _} => mentions(postfix, param),
+        _ => false
+    }
+}
+
+// The name a rule or a call goes by in `classify`, along with the rule it stands
+// for if that's a definition. Calls are named after how they're written, so the
+// same arguments always give the same name
+fn instance(node: &nodes::Node, context: &context::Context) -> (String, Option<nodes::Node>) {
+    match node {
+        nodes::Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => match context.symbols.get(identifier) {
+            Some(lazy) if lazy.params.is_empty() => (identifier.clone(), Some(lazy.fun.copy())),
+            _ => (identifier.clone(), None)
+        },
+        nodes::Node::RuleCallNode{identifier, args, pos_start: _, pos_end: _} => {
+            let name = formatter::show(node, formatter::ANYTHING);
+            match context.symbols.get(identifier) {
+                Some(lazy) if lazy.params.len() == args.len() => (name, Some(lazy.fun.bind(&lazy.params, args))),
+                _ => (name, None)
+            }
+        },
+        _ => (String::new(), None)
+    }
+}
+
+fn rule_name(node: &nodes::Node) -> String {
+    match node {
+        nodes::Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => identifier.clone(),
+        nodes::Node::RuleCallNode{identifier, args: _, pos_start: _, pos_end: _} => identifier.clone(),
+        _ => String::new()
+    }
+}
+
+// The rules and calls in `node`, the arguments of a call are part of what it
+// turns into so they're left to it
+fn calls<'n>(node: &'n nodes::Node, used: &mut Vec<&'n nodes::Node>) {
+    match node {
+        nodes::Node::RuleAccessNode{identifier: _, pos_start: _, pos_end: _} => used.push(node),
+        nodes::Node::RuleCallNode{identifier: _, args: _, pos_start: _, pos_end: _} => used.push(node),
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => chain.iter().for_each(|rule| calls(rule, used)),
+        nodes::Node::BinOpNode{left, op: _, right, pos_start: _, pos_end: _} => {
+            calls(left, used);
+            calls(right, used);
+        },
+        nodes::Node::PostFixNode{postfix, op: _, pos_start: _, pos_end: _} => calls(postfix, used),
+        _ => ()
+    }
+}
+
+// The rules and calls `node` can get to before it's consumed anything
+fn leftmost<'n>(node: &'n nodes::Node, context: &context::Context, analysis: &Analysis, growing: &HashSet<&String>, visiting: &mut Vec<String>, used: &mut Vec<&'n nodes::Node>) {
+    match node {
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => {
+            for rule in chain {
+                leftmost(rule, context, analysis, growing, visiting, used);
+                if !nullable(rule, context, analysis, growing, visiting) {
+                    break
+                }
+            }
+        },
+        nodes::Node::BinOpNode{left, op: _, right, pos_start: _, pos_end: _} => {
+            leftmost(left, context, analysis, growing, visiting, used);
+            leftmost(right, context, analysis, growing, visiting, used);
+        },
+        nodes::Node::PostFixNode{postfix, op: _, pos_start: _, pos_end: _} => leftmost(postfix, context, analysis, growing, visiting, used),
+        nodes::Node::RuleAccessNode{identifier: _, pos_start: _, pos_end: _} => used.push(node),
+        nodes::Node::RuleCallNode{identifier: _, args: _, pos_start: _, pos_end: _} => used.push(node),
+        _ => ()
+    }
+}
+
+// Like `First::nullable`, but calls get their arguments filled in instead of
+// being taken to match anything. A call that's already being looked at
+// further up is taken to be nullable, saying yes is always safe
+fn nullable(node: &nodes::Node, context: &context::Context, analysis: &Analysis, growing: &HashSet<&String>, visiting: &mut Vec<String>) -> bool {
+    match node {
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => chain.iter().all(|rule| nullable(rule, context, analysis, growing, visiting)),
+        nodes::Node::BinOpNode{left, op: _, right, pos_start: _, pos_end: _} => nullable(left, context, analysis, growing, visiting) || nullable(right, context, analysis, growing, visiting),
+        nodes::Node::PostFixNode{postfix, op, pos_start: _, pos_end: _} => *op != Plus || nullable(postfix, context, analysis, growing, visiting),
+        nodes::Node::RuleCallNode{identifier, args: _, pos_start: _, pos_end: _} => match instance(node, context) {
+            (name, Some(instance)) if !visiting.contains(&name) && visiting.len() < if growing.contains(identifier) { GROWING_INSTANCES } else { MAX_INSTANCES } => {
+                visiting.push(name);
+                let res = nullable(&instance, context, analysis, growing, visiting);
+                visiting.pop();
+                res
+            },
+            _ => true
+        },
+        _ => analysis.first(node).nullable
+    }
+}
+
+// The characters `c` matches in a string, `caseless` or not
+pub fn char_set(c: char, caseless: bool) -> Ranges {
+    let mut set: Ranges = if caseless {
+        caseFolding::equivalents(c).iter().map(|e| (*e as u32, *e as u32)).collect()
+    } else {
+        vec![(c as u32, c as u32)]
+    };
+    normalise(&mut set);
+    return set
+}
+
+pub fn normalise(ranges: &mut Ranges) {
+    ranges.sort();
+    let mut merged: Ranges = Vec::new();
+    for (lo, hi) in ranges.iter() {
+        match merged.last_mut() {
+            Some((_, last_hi)) if *lo <= last_hi.saturating_add(1) => *last_hi = std::cmp::max(*last_hi, *hi),
+            _ => merged.push((*lo, *hi))
+        }
+    };
+    *ranges = merged;
+}
+
+pub fn union(a: &Ranges, b: &Ranges) -> Ranges {
+    let mut res = a.clone();
+    res.extend(b.iter());
+    normalise(&mut res);
+    return res
+}
+
+pub fn intersects(a: &Ranges, b: &Ranges) -> bool {
+    return a.iter().any(|(lo, hi)| b.iter().any(|(other_lo, other_hi)| lo <= other_hi && other_lo <= hi))
+}
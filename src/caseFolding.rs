@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+// Unicode simple case folding, `to_lowercase` already agrees with it for nearly
+// every character so only the ones where it doesn't are listed here
+pub fn fold(c: char) -> char {
+    match c {
+        '\u{3c2}' => '\u{3c3}',
+        '\u{3d0}' => '\u{3b2}',
+        '\u{3d1}' => '\u{3b8}',
+        '\u{3d5}' => '\u{3c6}',
+        '\u{3d6}' => '\u{3c0}',
+        '\u{3f0}' => '\u{3ba}',
+        '\u{3f1}' => '\u{3c1}',
+        '\u{3f5}' => '\u{3b5}',
+        '\u{17f}' => 's',
+        '\u{345}' => '\u{3b9}',
+        '\u{1e9b}' => '\u{1e61}',
+        '\u{1fbe}' => '\u{3b9}',
+        _ => {
+            let mut lower = c.to_lowercase();
+            match (lower.next(), lower.next()) {
+                (Some(l), None) => l,
+                _ => c
+            }
+        }
+    }
+}
+
+pub fn fold_str(string: &str) -> String {
+    return string.chars().map(fold).collect()
+}
+
+// Every character that folds to the same thing as `c`, including `c` itself
+pub fn equivalents(c: char) -> Vec<char> {
+    static FOLDED_FROM: OnceLock<HashMap<char, Vec<char>>> = OnceLock::new();
+    let table = FOLDED_FROM.get_or_init(|| {
+        let mut table: HashMap<char, Vec<char>> = HashMap::new();
+        for other in (0u32..=0x10ffff).filter_map(char::from_u32) {
+            let folded = fold(other);
+            if folded != other {
+                table.entry(folded).or_default().push(other);
+            }
+        };
+        table
+    });
+    let folded = fold(c);
+    let mut chars = vec![folded];
+    if let Some(others) = table.get(&folded) {
+        chars.extend(others.iter());
+    };
+    return chars
+}
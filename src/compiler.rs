@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+use crate::nodes;
+use crate::context;
+use crate::error;
+use crate::position;
+use crate::prelude;
+use crate::interpreter;
+use crate::analysis;
+use crate::vm::{Instruction, Dispatch};
+use crate::tokenTypes::TokenTypes::*;
+
+const MAX_EXPANSIONS: usize = 1000;
+
+pub struct Program {
+    pub instructions: Vec<Instruction>,
+    pub entries: HashMap<String, usize>,
+    pub arities: HashMap<String, usize>,
+    pub spans: Vec<(position::Position, position::Position)>,
+    pub tables: Vec<Dispatch>,
+    pub errors: Vec<error::Error>,
+    pub pos_start: position::Position,
+    pub pos_end: position::Position,
+    pub options: context::MatchOptions
+}
+
+impl Program {
+    pub fn entry(&self, rule: &str) -> Result<usize, error::Error> {
+        match self.entries.get(rule) {
+            Some(address) => Ok(*address),
+            None => match self.arities.get(rule) {
+                Some(arity) => Err(interpreter::arity_error(&rule.to_string(), *arity, 0, self.pos_start.copy(), self.pos_end.copy())),
+                None => Err(
+                    error::Error{
+                        name: "NoDefinitionError".to_string(),
+                        message: format!("No definition for {} found", rule),
+                        pos_start: self.pos_start.copy(),
+                        pos_end: self.pos_end.copy()
+                    }
+                )
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut labels: Vec<(&usize, &String)> = self.entries.iter().map(|(name, address)| (address, name)).collect();
+        labels.sort();
+        for (address, instruction) in self.instructions.iter().enumerate() {
+            for (_, name) in labels.iter().filter(|(a, _)| **a == address) {
+                writeln!(f, "{}:", name)?;
+            };
+            writeln!(f, "    {:>4}  {}", address, instruction)?;
+        };
+        Ok(())
+    }
+}
+
+struct Compiler<'a> {
+    context: &'a context::Context,
+    analysis: analysis::Analysis<'a>,
+    program: Program,
+    instances: HashMap<String, usize>,
+    pending: Vec<(String, nodes::Node)>,
+    calls: Vec<(usize, String)>,
+    expansions: usize
+}
+
+pub fn compile(ast: &nodes::Node) -> Result<Program, error::Error> {
+    match interpreter::definitions(ast) {
+        Ok(context) => compile_context(&context),
+        Err(err) => Err(err)
+    }
+}
+
+pub fn compile_context(context: &context::Context) -> Result<Program, error::Error> {
+    let (pos_start, pos_end) = context.pos();
+    let mut compiler = Compiler{
+        context,
+        analysis: analysis::analyse(context),
+        program: Program{
+            instructions: vec![Instruction::End],
+            entries: HashMap::new(),
+            arities: HashMap::new(),
+            spans: Vec::new(),
+            tables: Vec::new(),
+            errors: Vec::new(),
+            pos_start,
+            pos_end,
+            options: context.options.clone()
+        },
+        instances: HashMap::new(),
+        pending: Vec::new(),
+        calls: Vec::new(),
+        expansions: 0
+    };
+    let mut names: Vec<&String> = context.symbols.keys().collect();
+    names.sort();
+    for name in names {
+        let lazy = &context.symbols[name];
+        if lazy.params.is_empty() {
+            compiler.instance(name.clone(), lazy.fun.copy());
+        } else {
+            compiler.program.arities.insert(name.clone(), lazy.params.len());
+        }
+    };
+    let growing = analysis::growing(context);
+    while let Some((key, body)) = compiler.pending.pop() {
+        // Checked before writing any of its copies out, since their arguments
+        // can get too big to work with well before there's too many of them
+        let rule = key.split('(').next().unwrap_or("");
+        if let Some((name, call)) = growing.iter().find(|(name, _)| name.as_str() == rule) {
+            let (pos_start, pos_end) = call.get_pos();
+            return Err(
+                error::Error{
+                    name: "CompileError".to_string(),
+                    message: format!("{} ends up calling itself with bigger arguments than it got, only the interpreter can match it", name),
+                    pos_start,
+                    pos_end
+                }
+            )
+        };
+        if compiler.expansions > MAX_EXPANSIONS {
+            let (pos_start, pos_end) = body.get_pos();
+            return Err(
+                error::Error{
+                    name: "CompileError".to_string(),
+                    message: format!("Expanding {} needs more than {} copies of parameterised definitions", key, MAX_EXPANSIONS),
+                    pos_start,
+                    pos_end
+                }
+            )
+        };
+        let address = compiler.program.instructions.len();
+        compiler.instances.insert(key.clone(), address);
+        compiler.compile(&body);
+        compiler.emit(Instruction::Return);
+        if !key.contains('(') {
+            compiler.program.entries.insert(key, address);
+        }
+    };
+    let calls: Vec<(usize, String)> = compiler.calls.drain(..).collect();
+    for (at, key) in calls {
+        let target = compiler.instances[&key];
+        compiler.patch(at, target);
+    };
+    return Ok(compiler.program)
+}
+
+impl<'a> Compiler<'a> {
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.program.instructions.push(instruction);
+        return self.program.instructions.len() - 1
+    }
+
+    fn here(&self) -> usize {
+        return self.program.instructions.len()
+    }
+
+    fn patch(&mut self, at: usize, target: usize) {
+        match &mut self.program.instructions[at] {
+            Instruction::Choice(t) | Instruction::Commit(t) | Instruction::Call(t, _) => *t = target,
+            _ => panic!("Can't patch a jump into {}", self.program.instructions[at])
+        }
+    }
+
+    fn span(&mut self, pos_start: &position::Position, pos_end: &position::Position) -> usize {
+        self.program.spans.push((pos_start.copy(), pos_end.copy()));
+        return self.program.spans.len() - 1
+    }
+
+    fn fail(&mut self, err: error::Error) {
+        self.program.errors.push(err);
+        let index = self.program.errors.len() - 1;
+        self.emit(Instruction::Fail(index));
+    }
+
+    fn instance(&mut self, key: String, body: nodes::Node) {
+        if !self.instances.contains_key(&key) {
+            if key.contains('(') {
+                self.expansions += 1;
+            };
+            self.instances.insert(key.clone(), 0);
+            self.pending.push((key, body));
+        }
+    }
+
+    fn call(&mut self, key: String, body: nodes::Node, pos_start: &position::Position, pos_end: &position::Position) {
+        self.instance(key.clone(), body);
+        let span = self.span(pos_start, pos_end);
+        let at = self.emit(Instruction::Call(0, span));
+        self.calls.push((at, key));
+    }
+
+    fn compile(&mut self, node: &nodes::Node) {
+        match node {
+            nodes::Node::StrNode{string, caseless, pos_start, pos_end} => {
+                let span = self.span(pos_start, pos_end);
+                let mut chars = string.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if !*caseless => self.emit(Instruction::Char(c, span)),
+                    _ => self.emit(Instruction::Literal(string.clone(), *caseless, span))
+                };
+            },
+            nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => {
+                for rule in chain {
+                    self.compile(rule)
+                }
+            },
+            nodes::Node::BinOpNode{left: _, op: Pipe, right: _, pos_start: _, pos_end: _} => {
+                let mut alternatives = Vec::new();
+                flatten(node, &mut alternatives);
+                let firsts: Vec<analysis::First> = alternatives.iter().map(|alternative| self.analysis.first(alternative)).collect();
+                let dispatch = dispatch_table(&firsts).map(|table| {
+                    self.program.tables.push(table);
+                    let index = self.program.tables.len() - 1;
+                    self.emit(Instruction::Dispatch(index));
+                    index
+                });
+                let mut starts = Vec::new();
+                let mut commits = Vec::new();
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    starts.push(self.here());
+                    if i + 1 == alternatives.len() {
+                        self.compile(alternative);
+                        break
+                    };
+                    let choice = self.emit(Instruction::Choice(0));
+                    self.compile(alternative);
+                    commits.push(self.emit(Instruction::Commit(0)));
+                    let next = self.here();
+                    self.patch(choice, next);
+                };
+                let after = self.here();
+                for commit in commits {
+                    self.patch(commit, after);
+                };
+                if let Some(index) = dispatch {
+                    self.program.tables[index].retarget(&starts);
+                }
+            },
+            nodes::Node::PostFixNode{postfix, op, pos_start: _, pos_end: _} => {
+                if *op == Plus {
+                    self.compile(postfix);
+                };
+                let start = self.here();
+                let choice = self.emit(Instruction::Choice(0));
+                self.compile(postfix);
+                let commit = self.emit(Instruction::Commit(0));
+                let after = self.here();
+                self.patch(choice, after);
+                self.patch(commit, if *op == QuestionMark { after } else { start });
+            },
+            nodes::Node::RuleAccessNode{identifier, pos_start, pos_end} => {
+                match self.context.symbols.get(identifier) {
+                    Some(lazy) if !lazy.params.is_empty() =>
+                        self.fail(interpreter::arity_error(identifier, lazy.params.len(), 0, pos_start.copy(), pos_end.copy())),
+                    Some(lazy) => self.call(identifier.clone(), lazy.fun.copy(), pos_start, pos_end),
+                    None => match prelude::lookup(identifier) {
+                        Some(builtin) => {
+                            let span = self.span(pos_start, pos_end);
+                            self.emit(Instruction::Builtin(builtin, identifier.clone(), span));
+                        },
+                        None => self.fail(no_definition(identifier, pos_start, pos_end))
+                    }
+                }
+            },
+            nodes::Node::RuleCallNode{identifier, args, pos_start, pos_end} => {
+                match self.context.symbols.get(identifier) {
+                    Some(lazy) if lazy.params.len() != args.len() =>
+                        self.fail(interpreter::arity_error(identifier, lazy.params.len(), args.len(), pos_start.copy(), pos_end.copy())),
+                    Some(lazy) => {
+                        let key = format!("{}({})", identifier, args.iter().map(shape).collect::<Vec<String>>().join(", "));
+                        self.call(key, lazy.fun.bind(&lazy.params, args), pos_start, pos_end)
+                    },
+                    None if prelude::lookup(identifier).is_some() =>
+                        self.fail(interpreter::arity_error(identifier, 0, args.len(), pos_start.copy(), pos_end.copy())),
+                    None => self.fail(no_definition(identifier, pos_start, pos_end))
+                }
+            },
+            _ => panic!("@::")
+        }
+    }
+}
+
+// `a | (b | c)` and `(a | b) | c` both try a, b and c in that order
+fn flatten<'n>(node: &'n nodes::Node, alternatives: &mut Vec<&'n nodes::Node>) {
+    match node {
+        nodes::Node::BinOpNode{left, op: Pipe, right, pos_start: _, pos_end: _} => {
+            flatten(left, alternatives);
+            flatten(right, alternatives);
+        },
+        _ => alternatives.push(node)
+    }
+}
+
+// For every next character, the first alternative that could possibly match there.
+// The ones before it are bound to fail so they can be skipped, and trying the rest
+// in order from there keeps ordered choice and its errors exactly as they were. When
+// no alternative fits the last one is still tried, since its error is the one to give.
+// Gives up when no character gets to skip anything
+fn dispatch_table(firsts: &[analysis::First]) -> Option<Dispatch> {
+    let last = firsts.len() - 1;
+    let first_for = |c: Option<u32>| firsts.iter().position(|first| first.nullable || c.is_some_and(|c| first.chars.iter().any(|(lo, hi)| *lo <= c && c <= *hi))).unwrap_or(last);
+    let mut ascii = [0usize; 128];
+    for (c, target) in ascii.iter_mut().enumerate() {
+        *target = first_for(Some(c as u32));
+    };
+    let mut bounds: Vec<u32> = firsts.iter().flat_map(|first| first.chars.iter().flat_map(|(lo, hi)| vec![*lo, hi.saturating_add(1)])).filter(|b| *b > 128).collect();
+    bounds.push(128);
+    bounds.sort();
+    bounds.dedup();
+    // Only the nullable alternatives can match at the end of the input or before a character none of them start with
+    let other = first_for(None);
+    let mut ranges: Vec<(u32, u32, usize)> = Vec::new();
+    for (i, lo) in bounds.iter().enumerate() {
+        let hi = match bounds.get(i + 1) {
+            Some(next) => next - 1,
+            None => char::MAX as u32
+        };
+        let target = first_for(Some(*lo));
+        if target == other {
+            continue
+        };
+        match ranges.last_mut() {
+            Some((_, last_hi, last_target)) if *last_target == target && *last_hi + 1 == *lo => *last_hi = hi,
+            _ => ranges.push((*lo, hi, target))
+        }
+    };
+    if ascii.iter().all(|target| *target == 0) && ranges.iter().all(|(_, _, target)| *target == 0) && other == 0 {
+        return None
+    };
+    return Some(Dispatch{ascii, ranges, other, end: other})
+}
+
+fn no_definition(identifier: &String, pos_start: &position::Position, pos_end: &position::Position) -> error::Error {
+    error::Error{
+        name: "NoDefinitionError".to_string(),
+        message: format!("No definition for {} found", identifier),
+        pos_start: pos_start.copy(),
+        pos_end: pos_end.copy()
+    }
+}
+
+// Unlike `Display` this keeps strings and rule names apart and remembers where
+// they were written, so errors from an expanded definition point at its own arguments
+fn shape(node: &nodes::Node) -> String {
+    match node {
+        nodes::Node::StrNode{string, caseless, pos_start, pos_end: _} => format!("{}{:?}@{}", if *caseless { "i" } else { "" }, string, pos_start.index),
+        nodes::Node::RuleAccessNode{identifier, pos_start, pos_end: _} => format!("{}@{}", identifier, pos_start.index),
+        nodes::Node::RuleCallNode{identifier, args, pos_start: _, pos_end: _} => format!("{}({})", identifier, args.iter().map(shape).collect::<Vec<String>>().join(", ")),
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => format!("({})", chain.iter().map(shape).collect::<Vec<String>>().join(" ")),
+        nodes::Node::BinOpNode{left, op, right, pos_start: _, pos_end: _} => format!("({} {} {})", shape(left), op, shape(right)),
+        nodes::Node::PostFixNode{postfix, op, pos_start: _, pos_end: _} => format!("({} {})", shape(postfix), op),
+        _ => format!("{}", node)
+    }
+}
@@ -1,19 +1,106 @@
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use crate::nodes;
+use crate::dfa;
+use crate::position;
+
+// Deep enough for any input that's sensibly nested, shallow enough that running
+// into it, which is what left recursion always does, takes tens of megabytes
+pub const DEFAULT_MAX_DEPTH: usize = 100_000;
+
+// A handle that can stop matches from another thread, every clone of it
+// shares the same flag
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    flag: Arc<AtomicBool>
+}
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        return CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        self.flag.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        return self.flag.load(Ordering::Relaxed)
+    }
+}
+
+// Which of the limits in `MatchOptions` a match ran into. The message of a
+// `ResourceLimitExceeded` error starts with the name of the option and a colon,
+// like `max_steps: `, which is what `Error::limit` reads back
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Limit {
+    Steps,
+    Depth,
+    MemoBytes,
+    Time
+}
+
+impl Limit {
+    pub fn option(&self) -> &'static str {
+        match self {
+            Limit::Steps => "max_steps",
+            Limit::Depth => "max_depth",
+            Limit::MemoBytes => "max_memo_bytes",
+            Limit::Time => "timeout"
+        }
+    }
+}
+
+// Limits on a single match, going over one stops the match with a
+// `ResourceLimitExceeded` error saying which one it was, see `Limit`. The depth
+// is how many definitions are being matched at once on either engine, and is
+// the only one limited by default. Steps are
+// nodes on the interpreter but instructions on the VM, and the VM keeps no memo
+// so `max_memo_bytes` only bounds the interpreter
+#[derive(Debug, Clone)]
+pub struct MatchOptions {
+    pub max_steps: Option<u64>,
+    pub max_depth: usize,
+    pub max_memo_bytes: Option<usize>,
+    pub timeout: Option<std::time::Duration>,
+    pub cancel: Option<CancelToken>
+}
+
+impl Default for MatchOptions {
+    fn default() -> MatchOptions {
+        MatchOptions{max_steps: None, max_depth: DEFAULT_MAX_DEPTH, max_memo_bytes: None, timeout: None, cancel: None}
+    }
+}
 
 pub struct Lazy {
     pub fun: Box<nodes::Node>,
+    pub params: Vec<String>,
+    // Shows up in parse trees without anything that matched inside it
+    pub atomic: bool
 }
 
 pub struct Context {
-    pub symbols:  HashMap<String, Lazy>
+    pub symbols:  HashMap<String, Lazy>,
+    pub dfas: HashMap<String, dfa::Dfa>,
+    pub pos_start: position::Position,
+    pub pos_end: position::Position,
+    pub options: MatchOptions
 }
 
 impl Context {
-    pub fn get(&mut self, name: String) -> Option<&Lazy> {
+    pub fn get(&self, name: String) -> Option<&Lazy> {
         return self.symbols.get(&name)
     }
 
+    pub fn pos(&self) -> (position::Position, position::Position) {
+        return (self.pos_start.copy(), self.pos_end.copy())
+    }
+
     pub fn set(&mut self, name: String, thunk: Lazy) {
         self.symbols.insert(name, thunk);
     }
@@ -0,0 +1,339 @@
+use std::collections::{HashMap, BTreeSet};
+use crate::nodes;
+use crate::context;
+use crate::prelude;
+use crate::analysis::{Ranges, char_set, union, intersects};
+use crate::tokenTypes::TokenTypes::*;
+
+const MAX_SIZE: usize = 10000;
+const MAX_STATES: usize = 2000;
+const DEAD: usize = usize::MAX;
+
+// A rule with every rule it uses written out in place, which is only
+// possible because the rules that end up here never reach themselves
+enum Regular {
+    Empty,
+    Set(Ranges),
+    Seq(Vec<Regular>),
+    Alt(Box<Regular>, Box<Regular>),
+    Opt(Box<Regular>),
+    Star(Box<Regular>),
+    Plus(Box<Regular>)
+}
+
+pub struct State {
+    ascii: [usize; 128],
+    ranges: Vec<(u32, u32, usize)>,
+    accepting: bool
+}
+
+pub struct Dfa {
+    pub states: Vec<State>
+}
+
+impl Dfa {
+    fn next(&self, state: usize, c: char) -> usize {
+        let state = &self.states[state];
+        let c = c as u32;
+        if c < 128 {
+            return state.ascii[c as usize]
+        };
+        match state.ranges.binary_search_by(|(lo, hi, _)| if *hi < c { std::cmp::Ordering::Less } else if *lo > c { std::cmp::Ordering::Greater } else { std::cmp::Ordering::Equal }) {
+            Ok(i) => state.ranges[i].2,
+            Err(_) => DEAD
+        }
+    }
+
+    // Length in bytes of the longest prefix of the input the rule matches. For
+    // the rules that get a DFA this is exactly where ordered choice would stop too
+    pub fn longest_match(&self, input: &str) -> Option<usize> {
+        return self.scan(input).0
+    }
+
+    // The longest match along with how many bytes were looked at to find it, which
+    // is one more than the input when it ran all the way to the end
+    pub fn scan(&self, input: &str) -> (Option<usize>, usize) {
+        let mut state = 0usize;
+        let mut last = if self.states[0].accepting { Some(0) } else { None };
+        for (i, c) in input.char_indices() {
+            state = self.next(state, c);
+            if state == DEAD {
+                return (last, i + c.len_utf8())
+            };
+            if self.states[state].accepting {
+                last = Some(i + c.len_utf8());
+            }
+        };
+        return (last, input.len() + 1)
+    }
+}
+
+pub fn compile_regular(context: &context::Context) -> HashMap<String, Dfa> {
+    let mut dfas = HashMap::new();
+    for (name, lazy) in context.symbols.iter() {
+        if !lazy.params.is_empty() {
+            continue
+        };
+        let mut size = 0usize;
+        let regular = match lower(&lazy.fun, context, &mut vec![name.clone()], &mut size) {
+            Some(a) => a,
+            None => continue
+        };
+        if !deterministic(&regular, &Vec::new()) {
+            continue
+        };
+        if let Some(dfa) = determinise(&regular) {
+            dfas.insert(name.clone(), dfa);
+        }
+    };
+    return dfas
+}
+
+fn lower(node: &nodes::Node, context: &context::Context, visiting: &mut Vec<String>, size: &mut usize) -> Option<Regular> {
+    *size += 1;
+    if *size > MAX_SIZE {
+        return None
+    };
+    match node {
+        nodes::Node::StrNode{string, caseless, pos_start: _, pos_end: _} => {
+            let chars = string.chars().map(|c| Regular::Set(char_set(c, *caseless))).collect::<Vec<Regular>>();
+            *size += chars.len();
+            if chars.is_empty() { Some(Regular::Empty) } else { Some(Regular::Seq(chars)) }
+        },
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => {
+            let mut seq = Vec::new();
+            for rule in chain {
+                seq.push(lower(rule, context, visiting, size)?);
+            };
+            Some(Regular::Seq(seq))
+        },
+        nodes::Node::BinOpNode{left, op: Pipe, right, pos_start: _, pos_end: _} => {
+            let left = lower(left, context, visiting, size)?;
+            let right = lower(right, context, visiting, size)?;
+            Some(Regular::Alt(Box::new(left), Box::new(right)))
+        },
+        nodes::Node::PostFixNode{postfix, op, pos_start: _, pos_end: _} => {
+            let inner = Box::new(lower(postfix, context, visiting, size)?);
+            match op {
+                QuestionMark => Some(Regular::Opt(inner)),
+                Asterisk => Some(Regular::Star(inner)),
+                Plus => Some(Regular::Plus(inner)),
+                _ => None
+            }
+        },
+        nodes::Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => {
+            match context.symbols.get(identifier) {
+                Some(lazy) if lazy.params.is_empty() && !visiting.contains(identifier) => {
+                    visiting.push(identifier.clone());
+                    let res = lower(&lazy.fun, context, visiting, size);
+                    visiting.pop();
+                    res
+                },
+                Some(_) => None,
+                None => prelude::ranges(identifier).map(Regular::Set)
+            }
+        },
+        _ => None
+    }
+}
+
+fn nullable(regular: &Regular) -> bool {
+    match regular {
+        Regular::Empty => true,
+        Regular::Set(_) => false,
+        Regular::Seq(seq) => seq.iter().all(nullable),
+        Regular::Alt(left, right) => nullable(left) || nullable(right),
+        Regular::Opt(_) | Regular::Star(_) => true,
+        Regular::Plus(inner) => nullable(inner)
+    }
+}
+
+fn first(regular: &Regular) -> Ranges {
+    match regular {
+        Regular::Empty => Vec::new(),
+        Regular::Set(set) => set.clone(),
+        Regular::Seq(seq) => {
+            let mut res = Vec::new();
+            for part in seq {
+                res = union(&res, &first(part));
+                if !nullable(part) {
+                    break
+                }
+            };
+            res
+        },
+        Regular::Alt(left, right) => union(&first(left), &first(right)),
+        Regular::Opt(inner) | Regular::Star(inner) | Regular::Plus(inner) => first(inner)
+    }
+}
+
+// Ordered choice and greedy repetition only agree with a DFA's longest match when
+// every decision can be made by looking at the next character, `follow` is what
+// can come right after `regular` inside the rule. Anything that can come after the
+// rule itself doesn't matter since the rule is free to stop there
+fn deterministic(regular: &Regular, follow: &Ranges) -> bool {
+    match regular {
+        Regular::Empty | Regular::Set(_) => true,
+        Regular::Seq(seq) => {
+            for (i, part) in seq.iter().enumerate() {
+                let rest = Regular::Seq(seq[i + 1..].iter().map(copy).collect());
+                let mut part_follow = first(&rest);
+                if nullable(&rest) {
+                    part_follow = union(&part_follow, follow);
+                };
+                if !deterministic(part, &part_follow) {
+                    return false
+                }
+            };
+            true
+        },
+        Regular::Alt(left, right) => {
+            // When the right one can match nothing, what comes after it could also start the left one
+            let clashes = nullable(right) && intersects(&first(left), follow);
+            !nullable(left)
+                && !intersects(&first(left), &first(right))
+                && !clashes
+                && deterministic(left, follow)
+                && deterministic(right, follow)
+        },
+        Regular::Opt(inner) => !nullable(inner) && !intersects(&first(inner), follow) && deterministic(inner, follow),
+        Regular::Star(inner) | Regular::Plus(inner) => {
+            !nullable(inner) && !intersects(&first(inner), follow) && deterministic(inner, &union(follow, &first(inner)))
+        }
+    }
+}
+
+fn copy(regular: &Regular) -> Regular {
+    match regular {
+        Regular::Empty => Regular::Empty,
+        Regular::Set(set) => Regular::Set(set.clone()),
+        Regular::Seq(seq) => Regular::Seq(seq.iter().map(copy).collect()),
+        Regular::Alt(left, right) => Regular::Alt(Box::new(copy(left)), Box::new(copy(right))),
+        Regular::Opt(inner) => Regular::Opt(Box::new(copy(inner))),
+        Regular::Star(inner) => Regular::Star(Box::new(copy(inner))),
+        Regular::Plus(inner) => Regular::Plus(Box::new(copy(inner)))
+    }
+}
+
+struct Nfa {
+    epsilons: Vec<Vec<usize>>,
+    edges: Vec<Vec<(u32, u32, usize)>>
+}
+
+impl Nfa {
+    fn state(&mut self) -> usize {
+        self.epsilons.push(Vec::new());
+        self.edges.push(Vec::new());
+        return self.epsilons.len() - 1
+    }
+
+    fn fragment(&mut self, regular: &Regular) -> (usize, usize) {
+        let start = self.state();
+        let end = match regular {
+            Regular::Empty => start,
+            Regular::Set(set) => {
+                let end = self.state();
+                for (lo, hi) in set {
+                    self.edges[start].push((*lo, *hi, end));
+                };
+                end
+            },
+            Regular::Seq(seq) => {
+                let mut end = start;
+                for part in seq {
+                    let (part_start, part_end) = self.fragment(part);
+                    self.epsilons[end].push(part_start);
+                    end = part_end;
+                };
+                end
+            },
+            Regular::Alt(left, right) => {
+                let end = self.state();
+                for branch in [left, right].iter() {
+                    let (branch_start, branch_end) = self.fragment(branch);
+                    self.epsilons[start].push(branch_start);
+                    self.epsilons[branch_end].push(end);
+                };
+                end
+            },
+            Regular::Opt(inner) | Regular::Star(inner) | Regular::Plus(inner) => {
+                let end = self.state();
+                let (inner_start, inner_end) = self.fragment(inner);
+                self.epsilons[start].push(inner_start);
+                self.epsilons[inner_end].push(end);
+                match regular {
+                    Regular::Opt(_) => self.epsilons[start].push(end),
+                    Regular::Star(_) => {
+                        self.epsilons[start].push(end);
+                        self.epsilons[inner_end].push(inner_start);
+                    },
+                    _ => self.epsilons[inner_end].push(inner_start)
+                };
+                end
+            }
+        };
+        return (start, end)
+    }
+
+    fn closure(&self, states: BTreeSet<usize>) -> BTreeSet<usize> {
+        let mut closed = states.clone();
+        let mut todo: Vec<usize> = states.into_iter().collect();
+        while let Some(state) = todo.pop() {
+            for next in self.epsilons[state].iter() {
+                if closed.insert(*next) {
+                    todo.push(*next);
+                }
+            }
+        };
+        return closed
+    }
+}
+
+fn determinise(regular: &Regular) -> Option<Dfa> {
+    let mut nfa = Nfa{epsilons: Vec::new(), edges: Vec::new()};
+    let (start, accept) = nfa.fragment(regular);
+    let mut sets: Vec<BTreeSet<usize>> = vec![nfa.closure(vec![start].into_iter().collect())];
+    let mut index: HashMap<BTreeSet<usize>, usize> = HashMap::new();
+    index.insert(sets[0].clone(), 0);
+    let mut states = Vec::new();
+    let mut current = 0;
+    while current < sets.len() {
+        let edges: Vec<(u32, u32, usize)> = sets[current].iter().flat_map(|s| nfa.edges[*s].iter().cloned()).collect();
+        let mut bounds: Vec<u32> = edges.iter().flat_map(|(lo, hi, _)| vec![*lo, hi + 1]).collect();
+        bounds.sort();
+        bounds.dedup();
+        let mut ranges: Vec<(u32, u32, usize)> = Vec::new();
+        for pair in bounds.windows(2) {
+            let (lo, hi) = (pair[0], pair[1] - 1);
+            let targets: BTreeSet<usize> = edges.iter().filter(|(l, h, _)| *l <= lo && hi <= *h).map(|(_, _, t)| *t).collect();
+            if targets.is_empty() {
+                continue
+            };
+            let target = nfa.closure(targets);
+            let next = match index.get(&target) {
+                Some(a) => *a,
+                None => {
+                    if sets.len() >= MAX_STATES {
+                        return None
+                    };
+                    sets.push(target.clone());
+                    index.insert(target, sets.len() - 1);
+                    sets.len() - 1
+                }
+            };
+            match ranges.last_mut() {
+                Some((_, last_hi, last)) if *last == next && *last_hi + 1 == lo => *last_hi = hi,
+                _ => ranges.push((lo, hi, next))
+            }
+        };
+        let mut ascii = [DEAD; 128];
+        for (lo, hi, next) in ranges.iter() {
+            for c in *lo..=std::cmp::min(*hi, 127) {
+                ascii[c as usize] = *next;
+            }
+        };
+        states.push(State{ascii, ranges, accepting: sets[current].contains(&accept)});
+        current += 1;
+    };
+    return Some(Dfa{states})
+}
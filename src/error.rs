@@ -1,4 +1,5 @@
 use crate::position;
+use crate::context;
 
 #[derive(Debug)]
 pub struct Error {
@@ -12,6 +13,19 @@ impl Error {
     pub fn copy(&self) -> Error {
         Error{name: self.name.clone(), message: self.message.clone(), pos_start: self.pos_start.copy(), pos_end: self.pos_end.copy()}
     }
+
+    // Which limit a `ResourceLimitExceeded` error ran into, going by how its message starts
+    pub fn limit(&self) -> Option<context::Limit> {
+        if self.name != "ResourceLimitExceeded" {
+            return None
+        };
+        let option = match self.message.split_once(':') {
+            Some((option, _)) => option,
+            None => return None
+        };
+        let limits = [context::Limit::Steps, context::Limit::Depth, context::Limit::MemoBytes, context::Limit::Time];
+        return limits.iter().copied().find(|limit| limit.option() == option)
+    }
 }
 
 impl std::fmt::Display for Error {
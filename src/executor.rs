@@ -0,0 +1,33 @@
+// Something to run on another thread, which can borrow from whoever handed it over
+pub type Job<'j> = Box<dyn FnOnce() + Send + 'j>;
+
+// Runs batches of jobs for `Grammar::is_match_all_with`, so matching many inputs can
+// go through a thread pool the program already has instead of new threads every time
+pub trait Executor {
+    // How many jobs a batch should be split into
+    fn workers(&self) -> usize;
+
+    // Has to have run every job before it returns, on whatever threads it likes
+    fn execute<'j>(&self, jobs: Vec<Job<'j>>);
+}
+
+// Starts a thread for every job and waits for all of them, what `is_match_all` uses
+// when it isn't given anything else
+pub struct Threads(pub usize);
+
+impl Executor for Threads {
+    fn workers(&self) -> usize {
+        return self.0.max(1)
+    }
+
+    fn execute<'j>(&self, jobs: Vec<Job<'j>>) {
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = jobs.into_iter().map(|job| scope.spawn(job)).collect();
+            for handle in handles {
+                if let Err(panic) = handle.join() {
+                    std::panic::resume_unwind(panic)
+                }
+            }
+        })
+    }
+}
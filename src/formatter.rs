@@ -0,0 +1,178 @@
+use crate::position;
+use crate::lexer;
+use crate::parser;
+use crate::nodes;
+use crate::error;
+use crate::tokenTypes;
+use crate::tokenTypes::TokenTypes::*;
+
+// What can be written somewhere without parenthesis around it: anything at
+// all, anything but alternatives, or only something a postfix can go after
+pub const ANYTHING: u8 = 0;
+pub const SEQUENCE: u8 = 1;
+pub const ATOM: u8 = 2;
+
+enum Item {
+    Rule(Box<nodes::Node>, Vec<String>),
+    Comment(String)
+}
+
+// Rewrites a grammar the canonical way: one rule per line, the `:=` of rules
+// that aren't separated by a blank line lined up, no more parenthesis than it
+// takes, literals in single quotes and sets of single characters in double
+// quotes. Comments stay where they were, those after a rule go at the end of
+// its line
+pub fn format(source: &str) -> Result<String, error::Error> {
+    let position = position::Position{filename: String::from("Yoi"), ftext: std::sync::Arc::from(source), index: 0u64, ln: 1u64, cn: 1u64};
+    let mut lexer = lexer::Lexer{current_index: 0usize, chars: source.chars().collect(), position, comments: Vec::new()};
+    let mut toks = match lexer.lex() {
+        Ok(a) => a,
+        Err(err) => return Err(err)
+    };
+    toks.reverse();
+    let mut parser = parser::Parser{tokens: toks};
+    let rules = match parser.parse() {
+        Ok(nodes::Node::RulesNode{rules, pos_start: _, pos_end: _}) => rules,
+        Ok(_) => panic!("A grammar should always parse into rules"),
+        Err(err) => return Err(err)
+    };
+    let chars = &lexer.chars;
+    let line = |index: u64| chars[..index as usize].iter().filter(|c| **c == '\n').count();
+    let mut items: Vec<(usize, Item)> = rules.into_iter().map(|rule| {
+        let (pos_start, _) = rule.get_pos();
+        (line(pos_start.index), Item::Rule(Box::new(rule), Vec::new()))
+    }).collect();
+    for comment in lexer.comments.iter() {
+        let at = line(comment.pos_start.index);
+        let start = chars[..comment.pos_start.index as usize].iter().rposition(|c| *c == '\n').map_or(0, |i| i + 1);
+        let own_line = chars[start..comment.pos_start.index as usize].iter().all(|c| c.is_whitespace());
+        let text = comment.tok_value.trim_end().to_string();
+        let owner = items.iter_mut().rev().find(|(line, item)| *line <= at && matches!(item, Item::Rule(_, _)));
+        match owner {
+            Some((_, Item::Rule(_, trailing))) if !own_line => trailing.push(text),
+            _ => items.push((at, Item::Comment(text)))
+        }
+    };
+    items.sort_by_key(|(line, _)| *line);
+    let lines: Vec<&str> = source.split('\n').collect();
+    let blank = |line: usize| line > 0 && lines[line - 1].trim().is_empty();
+    // Rules between blank lines are lined up together
+    let mut blocks: Vec<Vec<Item>> = Vec::new();
+    for (i, (line, item)) in items.into_iter().enumerate() {
+        if i == 0 || blank(line) {
+            blocks.push(Vec::new());
+        };
+        if let Some(block) = blocks.last_mut() {
+            block.push(item);
+        }
+    };
+    let mut out = Vec::new();
+    for block in blocks {
+        let heads: Vec<String> = block.iter().map(|item| match item {
+            Item::Rule(rule, _) => head(rule),
+            Item::Comment(_) => String::new()
+        }).collect();
+        let width = heads.iter().map(|head| head.chars().count()).max().unwrap_or(0);
+        let mut lines = Vec::new();
+        for (item, head) in block.iter().zip(heads.iter()) {
+            lines.push(match item {
+                Item::Rule(rule, trailing) => match &**rule {
+                    nodes::Node::RuleNode{rhs: _, params: _, modifiers: _, lhs, pos_start: _, pos_end: _} => {
+                        let mut line = format!("{}{} := {}", head, " ".repeat(width - head.chars().count()), show(lhs, ANYTHING));
+                        for comment in trailing {
+                            line.push_str(&format!(" {}", comment));
+                        };
+                        line
+                    },
+                    _ => panic!("Rules should only have rules in them")
+                },
+                Item::Comment(comment) => comment.clone()
+            });
+        };
+        out.push(lines.join("\n"));
+    };
+    return Ok(format!("{}\n", out.join("\n\n")))
+}
+
+// Everything before the `:=`
+fn head(rule: &nodes::Node) -> String {
+    match rule {
+        nodes::Node::RuleNode{rhs, params, modifiers, lhs: _, pos_start: _, pos_end: _} => {
+            let modifiers: String = [Caret, Tilde, At].iter().filter(|m| modifiers.contains(m)).map(|m| nodes::modifier_symbol(*m)).collect();
+            if params.is_empty() {
+                format!("{}{}", modifiers, rhs)
+            } else {
+                format!("{}{}({})", modifiers, rhs, params.join(", "))
+            }
+        },
+        _ => panic!("Only rules have a head")
+    }
+}
+
+// The node written so it can go where only `place` can without parenthesis
+pub fn show(node: &nodes::Node, place: u8) -> String {
+    let (text, needs) = match node {
+        nodes::Node::StrNode{string, caseless, pos_start: _, pos_end: _} => literal(string, *caseless),
+        nodes::Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => (identifier.clone(), ATOM),
+        nodes::Node::RuleCallNode{identifier, args, pos_start: _, pos_end: _} =>
+            (format!("{}({})", identifier, args.iter().map(|arg| show(arg, ANYTHING)).collect::<Vec<String>>().join(", ")), ATOM),
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => {
+            if chain.len() == 1 {
+                return show(&chain[0], place)
+            };
+            (chain.iter().map(|rule| show(rule, SEQUENCE)).collect::<Vec<String>>().join(" "), SEQUENCE)
+        },
+        nodes::Node::BinOpNode{left, op: _, right, pos_start: _, pos_end: _} => match char_set(node) {
+            Some(set) => (set, ATOM),
+            None => (format!("{} | {}", show(left, ANYTHING), show(right, SEQUENCE)), ANYTHING)
+        },
+        nodes::Node::PostFixNode{postfix, op, pos_start: _, pos_end: _} => (format!("{}{}", show(postfix, ATOM), nodes::operator_symbol(*op)), SEQUENCE),
+        _ => (format!("{}", node), ATOM)
+    };
+    return if needs < place { format!("({})", text) } else { text }
+}
+
+// A literal can't have a single quote in it, so one that does is written as
+// several with the quotes in between, like `'it' "'" 's'`
+fn literal(string: &str, caseless: bool) -> (String, u8) {
+    let prefix = if caseless { "i" } else { "" };
+    if !string.contains('\'') {
+        return (format!("{}'{}'", prefix, string), ATOM)
+    };
+    let mut pieces = Vec::new();
+    for (i, piece) in string.split('\'').enumerate() {
+        if i > 0 {
+            pieces.push(format!("{}\"'\"", prefix));
+        };
+        if !piece.is_empty() {
+            pieces.push(format!("{}'{}'", prefix, piece));
+        }
+    };
+    return if pieces.len() == 1 { (pieces.remove(0), ATOM) } else { (pieces.join(" "), SEQUENCE) }
+}
+
+// Alternatives that are all a single character, written the way the lexer
+// would have turned them into those alternatives in the first place
+pub fn char_set(node: &nodes::Node) -> Option<String> {
+    let mut chars = Vec::new();
+    let mut caseless = Vec::new();
+    let mut pending = vec![node];
+    while let Some(node) = pending.pop() {
+        match node {
+            nodes::Node::BinOpNode{left, op: tokenTypes::TokenTypes::Pipe, right, pos_start: _, pos_end: _} => {
+                pending.push(right);
+                pending.push(left);
+            },
+            nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} if chain.len() == 1 => pending.push(&chain[0]),
+            nodes::Node::StrNode{string, caseless: is_caseless, pos_start: _, pos_end: _} if string.chars().count() == 1 && string != "\"" => {
+                chars.push(string.clone());
+                caseless.push(*is_caseless);
+            },
+            _ => return None
+        }
+    };
+    if caseless.iter().any(|c| *c != caseless[0]) {
+        return None
+    };
+    return Some(format!("{}\"{}\"", if caseless[0] { "i" } else { "" }, chars.join("")))
+}
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use crate::nodes;
+use crate::context;
+use crate::error;
+use crate::analysis;
+use crate::prefilter;
+use crate::interpreter;
+use crate::dfa;
+use crate::rayRengine::Grammar;
+
+// An entry rule and what every input it matches has to start with
+struct Entry {
+    rule: String,
+    prefix: String,
+    first: Option<analysis::First>
+}
+
+// Several grammars, or several entry rules of one grammar, matched against the
+// same input together. Definitions that are the same in different grammars are
+// kept once, so they're also only matched once at any place in the input
+pub struct GrammarSet {
+    pub context: context::Context,
+    entries: Vec<Entry>
+}
+
+impl GrammarSet {
+    // One entry for the `main` rule of every grammar, in the order given
+    pub fn new(grammars: Vec<String>) -> Result<GrammarSet, error::Error> {
+        let mut parsed = Vec::new();
+        for grammar in grammars {
+            match Grammar::new(grammar) {
+                Ok(a) => parsed.push(a),
+                Err(err) => return Err(err)
+            }
+        };
+        let entries = (0..parsed.len()).map(|i| (i, String::from("main"))).collect();
+        return Ok(GrammarSet::merge(parsed.into_iter().map(|grammar| grammar.context).collect(), entries))
+    }
+
+    // One entry for every rule named, all from the same grammar
+    pub fn rules(grammar: String, rules: Vec<&str>) -> Result<GrammarSet, error::Error> {
+        let grammar = match Grammar::new(grammar) {
+            Ok(a) => a,
+            Err(err) => return Err(err)
+        };
+        let entries = rules.iter().map(|rule| (0, rule.to_string())).collect();
+        return Ok(GrammarSet::merge(vec![grammar.context], entries))
+    }
+
+    fn merge(contexts: Vec<context::Context>, entries: Vec<(usize, String)>) -> GrammarSet {
+        // An entry a grammar doesn't define has to stay undefined, whatever the others define
+        let missing = entries.iter().filter(|(i, rule)| !contexts[*i].symbols.contains_key(rule)).map(|(_, rule)| rule.clone()).collect();
+        let names = shared_names(&contexts, missing);
+        let (pos_start, pos_end) = contexts[0].pos();
+        let mut context = context::Context{symbols: HashMap::new(), dfas: HashMap::new(), pos_start, pos_end, options: context::MatchOptions::default()};
+        for (i, other) in contexts.iter().enumerate() {
+            for (name, lazy) in other.symbols.iter() {
+                let merged = &names[&(i, name.clone())];
+                if !context.symbols.contains_key(merged) {
+                    let fun = Box::new(rename(&lazy.fun, &names, i, &lazy.params));
+                    context.set(merged.clone(), context::Lazy{fun, params: lazy.params.clone(), atomic: lazy.atomic});
+                }
+            }
+        };
+        context.dfas = dfa::compile_regular(&context);
+        let analysis = analysis::analyse(&context);
+        // Not trying an entry is only the same as trying it when trying it can't end in an error
+        let checked = prefilter::well_defined(&context);
+        let entries = entries.into_iter().map(|(i, rule)| {
+            let rule = names.get(&(i, rule.clone())).cloned().unwrap_or(rule);
+            let prefix = prefilter::extract(&context, &rule).prefix;
+            let (pos_start, pos_end) = context.pos();
+            let runs = checked && context.get(rule.clone()).is_some_and(|lazy| lazy.params.is_empty());
+            let first = if runs { Some(analysis.first(&nodes::Node::RuleAccessNode{identifier: rule.clone(), pos_start, pos_end})) } else { None };
+            Entry{rule, prefix, first}
+        }).collect();
+        return GrammarSet{context, entries}
+    }
+
+    pub fn len(&self) -> usize {
+        return self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.entries.is_empty()
+    }
+
+    pub fn set_options(&mut self, options: context::MatchOptions) {
+        self.context.options = options;
+    }
+
+    // The positions of the entries that match the whole input. Entries that can't
+    // start the way the input does aren't tried at all
+    pub fn matches(&self, input: String) -> Result<Vec<usize>, error::Error> {
+        let mut memo = interpreter::Memo::new();
+        let mut matched = Vec::new();
+        let next = input.chars().next();
+        for (i, entry) in self.entries.iter().enumerate() {
+            if !input.starts_with(&entry.prefix) {
+                continue
+            };
+            if let Some(first) = &entry.first {
+                if !first.nullable && !next.is_some_and(|c| first.contains(c)) {
+                    continue
+                }
+            };
+            match interpreter::run_context_rule_memo(&self.context, &entry.rule, &input, 0, &mut memo) {
+                Ok(end) => if end == input.len() { matched.push(i) },
+                Err(err) => if err.name != "InputError" { return Err(err) }
+            }
+        };
+        return Ok(matched)
+    }
+}
+
+// The name every definition goes by once the grammars are put together. Two
+// definitions share a name when they're written the same and everything they
+// use is shared as well, which is worked out the way a DFA gets minimised:
+// start from how each looks on its own and split until nothing changes. Names a
+// grammar uses without defining are never given out, so they keep meaning the
+// built-in rule, or nothing at all
+fn shared_names(contexts: &[context::Context], mut free: Vec<String>) -> HashMap<(usize, String), String> {
+    let mut rules: Vec<(usize, String)> = Vec::new();
+    for (i, context) in contexts.iter().enumerate() {
+        let mut names: Vec<&String> = context.symbols.keys().collect();
+        names.sort();
+        rules.extend(names.into_iter().map(|name| (i, name.clone())));
+    };
+    let mut shapes: Vec<(String, Vec<String>)> = Vec::new();
+    for (i, name) in rules.iter() {
+        let lazy = &contexts[*i].symbols[name];
+        let mut uses = Vec::new();
+        let shape = skeleton(&lazy.fun, &contexts[*i], &lazy.params, &mut uses, &mut free);
+        shapes.push((format!("{}({}) := {}", name, lazy.params.join(", "), shape), uses));
+    };
+    let index: HashMap<(usize, String), usize> = rules.iter().cloned().enumerate().map(|(n, rule)| (rule, n)).collect();
+    let mut classes: Vec<usize> = intern(shapes.iter().map(|(shape, _)| shape.clone()).collect());
+    loop {
+        let keys = rules.iter().enumerate().map(|(n, (i, _))| {
+            let uses: Vec<String> = shapes[n].1.iter().map(|used| classes[index[&(*i, used.clone())]].to_string()).collect();
+            format!("{} {}", classes[n], uses.join(" "))
+        }).collect();
+        let refined = intern(keys);
+        let count = |classes: &Vec<usize>| classes.iter().max().map_or(0, |max| max + 1);
+        if count(&refined) == count(&classes) {
+            break
+        };
+        classes = refined;
+    };
+    let mut chosen: HashMap<usize, String> = HashMap::new();
+    let mut taken: HashMap<String, usize> = HashMap::new();
+    let mut names = HashMap::new();
+    for (n, (i, name)) in rules.iter().enumerate() {
+        let merged = chosen.entry(classes[n]).or_insert_with(|| {
+            let merged = if taken.contains_key(name) || free.contains(name) { format!("{}@{}", name, i) } else { name.clone() };
+            taken.insert(merged.clone(), classes[n]);
+            merged
+        }).clone();
+        names.insert((*i, name.clone()), merged);
+    };
+    return names
+}
+
+fn intern(keys: Vec<String>) -> Vec<usize> {
+    let mut ids: HashMap<String, usize> = HashMap::new();
+    return keys.into_iter().map(|key| {
+        let next = ids.len();
+        *ids.entry(key).or_insert(next)
+    }).collect()
+}
+
+// How a definition is written with the definitions it uses left out, those
+// are collected into `uses` in the order they appear and anything else it
+// uses that isn't a parameter into `free`
+fn skeleton(node: &nodes::Node, context: &context::Context, params: &Vec<String>, uses: &mut Vec<String>, free: &mut Vec<String>) -> String {
+    match node {
+        nodes::Node::StrNode{string, caseless, pos_start: _, pos_end: _} => format!("{}{:?}", if *caseless { "i" } else { "" }, string),
+        nodes::Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => {
+            if !params.contains(identifier) && context.symbols.contains_key(identifier) {
+                uses.push(identifier.clone());
+                return String::from("$")
+            };
+            if !params.contains(identifier) {
+                free.push(identifier.clone());
+            };
+            identifier.clone()
+        },
+        nodes::Node::RuleCallNode{identifier, args, pos_start: _, pos_end: _} => {
+            let callee = if !params.contains(identifier) && context.symbols.contains_key(identifier) {
+                uses.push(identifier.clone());
+                String::from("$")
+            } else {
+                free.push(identifier.clone());
+                identifier.clone()
+            };
+            format!("{}({})", callee, args.iter().map(|arg| skeleton(arg, context, params, uses, free)).collect::<Vec<String>>().join(", "))
+        },
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => format!("({})", chain.iter().map(|rule| skeleton(rule, context, params, uses, free)).collect::<Vec<String>>().join(" ")),
+        nodes::Node::BinOpNode{left, op, right, pos_start: _, pos_end: _} => {
+            let left = skeleton(left, context, params, uses, free);
+            format!("({} {} {})", left, op, skeleton(right, context, params, uses, free))
+        },
+        nodes::Node::PostFixNode{postfix, op, pos_start: _, pos_end: _} => format!("({} {})", skeleton(postfix, context, params, uses, free), op),
+        _ => format!("{}", node)
+    }
+}
+
+fn rename(node: &nodes::Node, names: &HashMap<(usize, String), String>, grammar: usize, params: &Vec<String>) -> nodes::Node {
+    let renamed = |identifier: &String| -> String {
+        if params.contains(identifier) {
+            return identifier.clone()
+        };
+        names.get(&(grammar, identifier.clone())).cloned().unwrap_or(identifier.clone())
+    };
+    match node {
+        nodes::Node::RuleAccessNode{identifier, pos_start, pos_end} =>
+            nodes::Node::RuleAccessNode{identifier: renamed(identifier), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+        nodes::Node::RuleCallNode{identifier, args, pos_start, pos_end} =>
+            nodes::Node::RuleCallNode{identifier: renamed(identifier), args: args.iter().map(|arg| rename(arg, names, grammar, params)).collect(), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+        nodes::Node::RuleChainNode{chain, pos_start, pos_end} =>
+            nodes::Node::RuleChainNode{chain: chain.iter().map(|rule| rename(rule, names, grammar, params)).collect(), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+        nodes::Node::BinOpNode{left, op, right, pos_start, pos_end} =>
+            nodes::Node::BinOpNode{left: Box::new(rename(left, names, grammar, params)), op: *op, right: Box::new(rename(right, names, grammar, params)), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+        nodes::Node::PostFixNode{postfix, op, pos_start, pos_end} =>
+            nodes::Node::PostFixNode{postfix: Box::new(rename(postfix, names, grammar, params)), op: *op, pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+        _ => node.copy()
+    }
+}
@@ -0,0 +1,62 @@
+use crate::context;
+use crate::error;
+use crate::interpreter;
+
+// A rule matched against some text, along with everything the match found out
+// on the way, so the text can be edited and matched again without starting over
+pub struct Document {
+    text: String,
+    rule: String,
+    memo: interpreter::Memo,
+    // The grammar's generation when the memo was filled in
+    generation: u64,
+    // What `string_left_rule` would give for the text as it is now
+    pub result: Result<String, error::Error>
+}
+
+impl Document {
+    pub fn new(context: &context::Context, generation: u64, rule: &str, text: String) -> Document {
+        let mut document = Document{text, rule: rule.to_string(), memo: interpreter::Memo::new(), generation, result: Ok(String::new())};
+        document.result = document.rematch(context);
+        return document
+    }
+
+    pub fn text(&self) -> &str {
+        return &self.text
+    }
+
+    // How many matches of a definition at some place in the text are remembered
+    pub fn remembered(&self) -> usize {
+        return self.memo.len()
+    }
+
+    pub fn is_match(&self) -> Result<bool, error::Error> {
+        match &self.result {
+            Ok(left) => Ok(left.is_empty()),
+            Err(err) => if err.name == "InputError" { Ok(false) } else { Err(err.copy()) }
+        }
+    }
+
+    // Replaces the bytes in `range` with `replacement` and matches again, only what
+    // looked at the part that changed is matched from scratch. Panics like
+    // `String::replace_range` when the range isn't on character boundaries.
+    // Everything is matched from scratch when `generation` isn't the one the
+    // document was matched with, since the definitions have changed since
+    pub fn edit(&mut self, context: &context::Context, generation: u64, range: std::ops::Range<usize>, replacement: &str) {
+        self.text.replace_range(range.clone(), replacement);
+        if generation == self.generation {
+            self.memo.edit(range, replacement.len());
+        } else {
+            self.memo = interpreter::Memo::new();
+            self.generation = generation;
+        };
+        self.result = self.rematch(context);
+    }
+
+    fn rematch(&mut self, context: &context::Context) -> Result<String, error::Error> {
+        match interpreter::run_context_rule_memo(context, &self.rule, &self.text, 0, &mut self.memo) {
+            Ok(end) => Ok(self.text[end..].to_string()),
+            Err(err) => Err(err)
+        }
+    }
+}
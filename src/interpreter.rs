@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::rc::Rc;
 use crate::nodes;
 use crate::position;
 use crate::error;
@@ -6,89 +7,575 @@ use crate::tokenTypes;
 use crate::tokenTypes::TokenTypes::*;
 use crate::context;
 use crate::parserCombinators;
+use crate::prelude;
+use crate::dfa;
+use crate::tree;
+use crate::analysis;
 
-pub fn interpret(node: Box<nodes::Node>, context: &mut context::Context, input: Result<parserCombinators::Value, error::Error>) -> Result<parserCombinators::Value, error::Error> {
-    let node = *node;
+// What's left to do once the node being matched right now has finished. Frames
+// point into the definitions instead of copying them, so however deep a match
+// goes only costs a few words a level
+pub enum Frame<'a> {
+    Chain{chain: &'a Vec<nodes::Node>, next: usize, built: usize, scope: Env<'a>},
+    BinOp{op: tokenTypes::TokenTypes, right: &'a nodes::Node, input: parserCombinators::Value, scope: Env<'a>},
+    PostFix{postfix: &'a nodes::Node, op: tokenTypes::TokenTypes, input: parserCombinators::Value, repeated: bool, scope: Env<'a>},
+    Rule{identifier: &'a String, start: Option<usize>, outer: usize, from: usize, built: usize}
+}
+
+pub enum Step<'a> {
+    Eval(&'a nodes::Node, Env<'a>, parserCombinators::Value),
+    Return(Result<parserCombinators::Value, error::Error>)
+}
+
+// The arguments of the parameterised definition being matched. Every argument
+// is matched in the scope of the call it was written in, which gives the same
+// as writing it out in place of the parameter the way `Node::bind` does
+pub struct Scope<'a> {
+    params: &'a Vec<String>,
+    args: &'a Vec<nodes::Node>,
+    outer: Env<'a>
+}
+
+pub type Env<'a> = Option<Rc<Scope<'a>>>;
+
+// What a match keeps up to date besides its stack of frames
+struct Tracked<'t> {
+    memo: &'t mut Memo,
+    // How far into the input anything has been looked at since the innermost
+    // definition being memoised started
+    looked: usize,
+    trees: &'t mut Option<Vec<tree::Tree>>
+}
+
+// Rough size of one memo entry besides the strings in it
+const MEMO_ENTRY_BYTES: usize = std::mem::size_of::<(String, usize)>() + std::mem::size_of::<MemoEntry>();
+
+// How many steps go by between looking at the clock and for cancellation
+const POLL_INTERVAL: u64 = 1024;
+
+// Successful matches of a definition by where they started, only for matches that
+// started right after a chain element, since otherwise what's returned for an empty
+// match depends on what came before it. Can be shared between matches of the
+// same input against the same definitions, or carried over to an edited one
+#[derive(Default)]
+pub struct Memo {
+    entries: HashMap<(String, usize), MemoEntry>,
+    bytes: usize
+}
+
+// `looked` is where the input stopped mattering, the match is the same for any
+// input that's the same from where it started up to there
+struct MemoEntry {
+    matched: String,
+    end: usize,
+    looked: usize
+}
+
+impl Memo {
+    pub fn new() -> Memo {
+        return Memo{entries: HashMap::new(), bytes: 0}
+    }
+
+    pub fn len(&self) -> usize {
+        return self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.entries.is_empty()
+    }
+
+    // Keeps the entries that are still right once the bytes in `range` are replaced
+    // by `inserted` bytes. Ones that didn't look as far as the edit stay where they
+    // are, ones that started after it move along with the input, the rest are dropped
+    pub fn edit(&mut self, range: std::ops::Range<usize>, inserted: usize) {
+        let entries = std::mem::take(&mut self.entries);
+        let shift = |at: usize| at - range.end + range.start + inserted;
+        self.bytes = 0;
+        for ((identifier, start), entry) in entries {
+            let kept = if entry.looked <= range.start {
+                Some((start, entry))
+            } else if start >= range.end {
+                Some((shift(start), MemoEntry{matched: entry.matched, end: shift(entry.end), looked: shift(entry.looked)}))
+            } else {
+                None
+            };
+            if let Some((start, entry)) = kept {
+                self.bytes += identifier.len() + entry.matched.len() + MEMO_ENTRY_BYTES;
+                self.entries.insert((identifier, start), entry);
+            }
+        }
+    }
+}
+
+// `full` is the whole input, the match starts from where `input` says what's left of it starts
+pub fn interpret(node: &nodes::Node, context: &context::Context, full: &str, input: Result<parserCombinators::Value, error::Error>) -> Result<parserCombinators::Value, error::Error> {
+    return interpret_memo(node, context, full, input, &mut Memo::new())
+}
+
+pub fn interpret_memo(node: &nodes::Node, context: &context::Context, full: &str, input: Result<parserCombinators::Value, error::Error>, memo: &mut Memo) -> Result<parserCombinators::Value, error::Error> {
+    return walk(node, context, full, input, memo, &mut None)
+}
+
+// Matches like `interpret`, also giving back the trees of the definitions that matched
+pub fn interpret_tree(node: &nodes::Node, context: &context::Context, full: &str, input: Result<parserCombinators::Value, error::Error>) -> Result<(parserCombinators::Value, Vec<tree::Tree>), error::Error> {
+    let mut trees = Some(Vec::new());
+    match walk(node, context, full, input, &mut Memo::new(), &mut trees) {
+        Ok(value) => Ok((value, trees.unwrap_or_default())),
+        Err(err) => Err(err)
+    }
+}
+
+// Walks the grammar with a stack of frames on the heap rather than by recursing,
+// so how deeply the input can nest is only bounded by `context.options`. When
+// `trees` is there, every definition that matches adds its tree to it, taking
+// the trees added since it started as its children. A node that fails always
+// leaves it the way it found it, so only chains have to tidy up after
+// themselves. Memoised matches would have to keep a copy of their tree, so the
+// memo isn't used then
+fn walk<'a>(node: &'a nodes::Node, context: &'a context::Context, full: &str, input: Result<parserCombinators::Value, error::Error>, memo: &mut Memo, trees: &mut Option<Vec<tree::Tree>>) -> Result<parserCombinators::Value, error::Error> {
+    let input = match input {
+        Ok(a) => a,
+        Err(err) => return Err(err)
+    };
+    let options = &context.options;
+    let started = std::time::Instant::now();
+    let mut stack: Vec<Frame<'a>> = Vec::new();
+    let mut steps = 0u64;
+    let mut furthest = input.1;
+    let mut tracked = Tracked{memo, looked: 0, trees};
+    // Definitions being matched right now, which is what the depth limit counts
+    let mut calls = 0usize;
+    // Polling only comes round every so often, which a short match never gets to
+    if options.cancel.as_ref().is_some_and(|cancel| cancel.is_cancelled()) {
+        return Err(cancelled_error(full, furthest))
+    };
+    let mut step = Step::Eval(node, None, input);
+    loop {
+        step = match step {
+            Step::Eval(node, scope, input) => {
+                steps += 1;
+                furthest = furthest.max(input.1);
+                if steps.is_multiple_of(POLL_INTERVAL) && options.cancel.as_ref().is_some_and(|cancel| cancel.is_cancelled()) {
+                    return Err(cancelled_error(full, furthest))
+                };
+                let exceeded = if options.max_steps.is_some_and(|max| steps > max) {
+                    Some((context::Limit::Steps, format!("Took more than {} steps", options.max_steps.unwrap_or(0))))
+                } else if options.max_memo_bytes.is_some_and(|max| tracked.memo.bytes > max) {
+                    Some((context::Limit::MemoBytes, format!("Memo table grew past {} bytes", options.max_memo_bytes.unwrap_or(0))))
+                } else if options.timeout.is_some_and(|timeout| steps.is_multiple_of(POLL_INTERVAL) && started.elapsed() > timeout) {
+                    Some((context::Limit::Time, format!("Ran for longer than {:?}", options.timeout.unwrap_or_default())))
+                } else {
+                    None
+                };
+                if let Some((limit, message)) = exceeded {
+                    let (pos_start, pos_end) = node.get_pos();
+                    return Err(limit_error(limit, message, pos_start, pos_end))
+                };
+                let frames = stack.len();
+                let step = eval(node, scope, context, full, input, &mut stack, &mut tracked);
+                if stack.len() > frames && matches!(stack.last(), Some(Frame::Rule{identifier: _, start: _, outer: _, from: _, built: _})) {
+                    calls += 1;
+                    if calls > options.max_depth {
+                        let (pos_start, pos_end) = node.get_pos();
+                        return Err(depth_error(options.max_depth, pos_start, pos_end))
+                    }
+                };
+                step
+            },
+            Step::Return(res) => match stack.pop() {
+                Some(Frame::Rule{identifier, start, outer, from, built}) => {
+                    if let Ok((matched, end)) = &res {
+                        let end = *end;
+                        match &mut tracked.trees {
+                            Some(trees) => {
+                                let mut children = trees.split_off(built);
+                                if context.get(identifier.clone()).is_some_and(|lazy| lazy.atomic) {
+                                    children.clear();
+                                };
+                                if tree::silent(identifier) {
+                                    trees.extend(children);
+                                } else {
+                                    trees.push(tree::Tree{rule: identifier.clone(), start: from, end, children});
+                                }
+                            },
+                            None => if let Some(start) = start {
+                                tracked.memo.bytes += identifier.len() + matched.len() + MEMO_ENTRY_BYTES;
+                                tracked.memo.entries.insert((identifier.clone(), start), MemoEntry{matched: matched.clone(), end, looked: tracked.looked});
+                            }
+                        }
+                    };
+                    tracked.looked = tracked.looked.max(outer);
+                    calls -= 1;
+                    Step::Return(res)
+                },
+                Some(frame) => resume(frame, res, &mut stack, tracked.trees),
+                None => return res
+            }
+        }
+    }
+}
+
+fn eval<'a>(node: &'a nodes::Node, scope: Env<'a>, context: &'a context::Context, full: &str, input: parserCombinators::Value, stack: &mut Vec<Frame<'a>>, tracked: &mut Tracked) -> Step<'a> {
+    let at = input.1;
+    let built = tracked.trees.as_ref().map_or(0, |trees| trees.len());
     match node {
         nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => {
-            let mut input = input;
-            for rule in chain {
-                let second = parserCombinators::snd(
-                    match input {
-                        Ok(a) => a,
-                        Err(err) => return Err(err)
-                    }
-                );
-                input = interpret(Box::new(rule.copy()), context, Ok((String::from(""), second)))
+            if chain.is_empty() {
+                return Step::Return(Ok(input))
             };
-            match input {
-                Ok(a) => Ok(a),
-                Err(err) => return Err(err)
-            }
+            stack.push(Frame::Chain{chain, next: 1, built, scope: scope.clone()});
+            Step::Eval(&chain[0], scope, (String::from(""), parserCombinators::snd(input)))
+        },
+        nodes::Node::StrNode{string, caseless, pos_start, pos_end} => {
+            // A caseless character can be written with more or fewer bytes than the one it matches
+            tracked.looked = tracked.looked.max(at + if *caseless { 4 * string.chars().count() } else { string.len() });
+            Step::Return(parserCombinators::literal_parse(string.to_string(), *caseless, full, input, pos_start.copy(), pos_end.copy()))
         },
-        nodes::Node::StrNode{string, pos_start, pos_end} => parserCombinators::literal_parse(
-            string.to_string(),
-            match input {
-                Ok(a) => a,
-                Err(err) => return Err(err)
-            },
-            pos_start.copy(),
-            pos_end.copy()
-        ),
         nodes::Node::RuleAccessNode{identifier, pos_start, pos_end} => {
+            // A parameter stands for its argument, which belongs to the scope it was passed from
+            if let Some(scope) = &scope {
+                if let Some(i) = scope.params.iter().position(|param| param == identifier) {
+                    return Step::Eval(&scope.args[i], scope.outer.clone(), input)
+                }
+            };
+            let lazy_value = match context.get(identifier.to_string()) {
+                Some(a) => a,
+                None => match prelude::lookup(identifier) {
+                    Some(builtin) => {
+                        let res = parserCombinators::builtin_parse(builtin, identifier.clone(), full, input, pos_start.copy(), pos_end.copy());
+                        // Room for one more character after it, `NEWLINE` checks what follows a '\r'
+                        let end = match &res {
+                            Ok((_, end)) => *end,
+                            Err(_) => at
+                        };
+                        tracked.looked = tracked.looked.max(end + 4);
+                        return Step::Return(res)
+                    },
+                    None => return Step::Return(Err (
+                        error::Error{
+                            name: "NoDefinitionError".to_string(),
+                            message: format!("No definition for {} found", identifier).to_string(),
+                            pos_start: pos_start.copy(),
+                            pos_end: pos_end.copy()
+                        }
+                    ))
+                }
+            };
+            if !lazy_value.params.is_empty() {
+                return Step::Return(Err(arity_error(identifier, lazy_value.params.len(), 0, pos_start.copy(), pos_end.copy())))
+            };
+            let start = if input.0.is_empty() && tracked.trees.is_none() { Some(at) } else { None };
+            if let Some(start) = start {
+                if let Some(entry) = tracked.memo.entries.get(&(identifier.clone(), start)) {
+                    tracked.looked = tracked.looked.max(entry.looked);
+                    return Step::Return(Ok((entry.matched.clone(), entry.end)))
+                }
+            };
+            // Matching with the DFA would leave out the trees of the definitions it's made
+            // of, which atomic ones don't keep anyway
+            if let Some(dfa) = context.dfas.get(identifier).filter(|_| tracked.trees.is_none() || lazy_value.atomic) {
+                // When it doesn't match, matching the definition itself looks at what it needs to
+                if let (Some(end), scanned) = dfa.scan(&full[at..]) {
+                    tracked.looked = tracked.looked.max(at + scanned);
+                    if let Some(trees) = tracked.trees.as_mut().filter(|_| !tree::silent(identifier)) {
+                        trees.push(tree::Tree{rule: identifier.clone(), start: at, end: at + end, children: Vec::new()});
+                    };
+                    return Step::Return(Ok((full[at..at + end].to_string(), at + end)))
+                }
+            };
+            let outer = tracked.looked;
+            if let Some(start) = start {
+                tracked.looked = start;
+            };
+            stack.push(Frame::Rule{identifier, start, outer, from: at, built});
+            Step::Eval(&lazy_value.fun, None, input)
+        },
+        nodes::Node::RuleCallNode{identifier, args, pos_start, pos_end} => {
             let lazy_value = match context.get(identifier.to_string()) {
                 Some(a) => a,
-                None => return Err (
+                None if prelude::lookup(identifier).is_some() => return Step::Return(Err(arity_error(identifier, 0, args.len(), pos_start.copy(), pos_end.copy()))),
+                None => return Step::Return(Err (
                     error::Error{
                         name: "NoDefinitionError".to_string(),
                         message: format!("No definition for {} found", identifier).to_string(),
                         pos_start: pos_start.copy(),
                         pos_end: pos_end.copy()
                     }
-                )
+                ))
             };
-            interpret(Box::new(lazy_value.fun.copy()), context, input)
+            if lazy_value.params.len() != args.len() {
+                return Step::Return(Err(arity_error(identifier, lazy_value.params.len(), args.len(), pos_start.copy(), pos_end.copy())))
+            };
+            let inner = Scope{params: &lazy_value.params, args, outer: scope};
+            stack.push(Frame::Rule{identifier, start: None, outer: tracked.looked, from: at, built});
+            Step::Eval(&lazy_value.fun, Some(Rc::new(inner)), input)
+        },
+        nodes::Node::BinOpNode{left, op, right, pos_start: _, pos_end: _} => {
+            stack.push(Frame::BinOp{op: *op, right, input: input.clone(), scope: scope.clone()});
+            Step::Eval(left, scope, input)
+        },
+        nodes::Node::PostFixNode{postfix, op, pos_start: _, pos_end: _} => {
+            stack.push(Frame::PostFix{postfix, op: *op, input: input.clone(), repeated: false, scope: scope.clone()});
+            Step::Eval(postfix, scope, input)
         },
-        nodes::Node::BinOpNode{left, op, right, pos_start: _, pos_end: _} => parserCombinators::handle_binary_op(left, op, right, context, input),
-        nodes::Node::PostFixNode{postfix, op, pos_start: _, pos_end: _} => parserCombinators::handle_postfix_op(postfix, op, context, input),
         _ => {
             panic!("@::")
         }
     }
 }
 
-fn add_def_pass(node: Box<nodes::Node>, context: &mut context::Context) {
+fn resume<'a>(frame: Frame<'a>, res: Result<parserCombinators::Value, error::Error>, stack: &mut Vec<Frame<'a>>, trees: &mut Option<Vec<tree::Tree>>) -> Step<'a> {
+    match frame {
+        Frame::Chain{chain, next, built, scope} => {
+            let value = match res {
+                Ok(a) => a,
+                Err(err) => {
+                    if let Some(trees) = trees {
+                        trees.truncate(built);
+                    };
+                    return Step::Return(Err(err))
+                }
+            };
+            if next == chain.len() {
+                return Step::Return(Ok(value))
+            };
+            stack.push(Frame::Chain{chain, next: next + 1, built, scope: scope.clone()});
+            Step::Eval(&chain[next], scope, (String::from(""), parserCombinators::snd(value)))
+        },
+        Frame::BinOp{op, right, input, scope} => parserCombinators::handle_binary_op(op, right, scope, input, res),
+        Frame::PostFix{postfix, op, input, repeated, scope} => parserCombinators::handle_postfix_op((postfix, scope), op, input, repeated, res, stack),
+        Frame::Rule{identifier: _, start: _, outer: _, from: _, built: _} => Step::Return(res)
+    }
+}
+
+// Points into the input rather than the grammar, at the furthest the match got
+pub fn cancelled_error(input: &str, furthest: usize) -> error::Error {
+    let position = position::Position::locate("input", input, furthest);
+    error::Error{
+        name: "Cancelled".to_string(),
+        message: "The match was cancelled".to_string(),
+        pos_start: position.copy(),
+        pos_end: position
+    }
+}
+
+// The message starts with which of the limits in `MatchOptions` it was
+pub fn limit_error(limit: context::Limit, message: String, pos_start: position::Position, pos_end: position::Position) -> error::Error {
+    error::Error{
+        name: "ResourceLimitExceeded".to_string(),
+        message: format!("{}: {}", limit.option(), message),
+        pos_start,
+        pos_end
+    }
+}
+
+pub fn depth_error(max_depth: usize, pos_start: position::Position, pos_end: position::Position) -> error::Error {
+    return limit_error(context::Limit::Depth, format!("More than {} definitions were being matched at once", max_depth), pos_start, pos_end)
+}
+
+pub fn arity_error(identifier: &String, expected: usize, got: usize, pos_start: position::Position, pos_end: position::Position) -> error::Error {
+    error::Error{
+        name: "ArgumentError".to_string(),
+        message: format!("{} expects {} arguments, got {}", identifier, expected, got),
+        pos_start,
+        pos_end
+    }
+}
+
+// Every use of a definition inside one with `params` passes it as many arguments as it
+// takes. Definitions that don't exist are left for matching to report, like before
+fn check_arity(node: &nodes::Node, context: &context::Context, params: &Vec<String>) -> Result<(), error::Error> {
+    match node {
+        nodes::Node::RuleAccessNode{identifier, pos_start, pos_end} => {
+            if params.contains(identifier) {
+                return Ok(())
+            };
+            match context.symbols.get(identifier) {
+                Some(lazy) if !lazy.params.is_empty() => Err(arity_error(identifier, lazy.params.len(), 0, pos_start.copy(), pos_end.copy())),
+                _ => Ok(())
+            }
+        },
+        nodes::Node::RuleCallNode{identifier, args, pos_start, pos_end} => {
+            let expected = match context.symbols.get(identifier) {
+                Some(lazy) => lazy.params.len(),
+                None if prelude::lookup(identifier).is_some() => 0,
+                None => args.len()
+            };
+            if expected != args.len() {
+                return Err(arity_error(identifier, expected, args.len(), pos_start.copy(), pos_end.copy()))
+            };
+            for arg in args {
+                if let Err(err) = check_arity(arg, context, params) {
+                    return Err(err)
+                }
+            };
+            return Ok(())
+        },
+        nodes::Node::BinOpNode{left, op: _, right, pos_start: _, pos_end: _} => match check_arity(left, context, params) {
+            Ok(()) => check_arity(right, context, params),
+            Err(err) => Err(err)
+        },
+        nodes::Node::PostFixNode{postfix, op: _, pos_start: _, pos_end: _} => check_arity(postfix, context, params),
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => {
+            for rule in chain {
+                if let Err(err) = check_arity(rule, context, params) {
+                    return Err(err)
+                }
+            };
+            return Ok(())
+        },
+        _ => Ok(())
+    }
+}
+
+fn add_def_pass(node: Box<nodes::Node>, context: &mut context::Context) -> Result<(), error::Error> {
     match *node {
         nodes::Node::RulesNode{rules, pos_start: _, pos_end: _} => {
+            let skip = String::from("skip");
+            let has_skip = rules.iter().any(|rule| match rule {
+                nodes::Node::RuleNode{rhs, params, modifiers: _, lhs: _, pos_start: _, pos_end: _} => *rhs == skip && params.is_empty(),
+                _ => false
+            });
+            let mut skipping = false;
             for rule in rules {
-                let (key, value) = match rule {
-                    nodes::Node::RuleNode{lhs, rhs, pos_start: _, pos_end: _} => (rhs.clone(), lhs),
+                let (key, params, value, atomic) = match rule {
+                    nodes::Node::RuleNode{lhs, rhs, params, modifiers, pos_start: _, pos_end: _} => {
+                        let lhs = if modifiers.contains(&Caret) { Box::new(lhs.caseless()) } else { lhs };
+                        let syntactic = modifiers.contains(&Tilde) && has_skip && *rhs != skip;
+                        skipping = skipping || syntactic;
+                        let lhs = if syntactic { Box::new(lhs.syntactic(&skip)) } else { lhs };
+                        (rhs.clone(), params, lhs, modifiers.contains(&At))
+                    },
                     _ => panic!("Not sure about that one")
                 };
-                let thunk = context::Lazy{fun: value};
+                let thunk = context::Lazy{fun: value, params, atomic};
                 context.set(key, thunk)
-            }
+            };
+            // `skip*` goes round forever on a `skip` that matches nothing
+            if skipping {
+                if let Some(lazy) = context.get(skip) {
+                    if analysis::analyse(context).first(&lazy.fun).nullable {
+                        let (pos_start, pos_end) = lazy.fun.get_pos();
+                        return Err(
+                            error::Error{
+                                name: "DefinitionError".to_string(),
+                                message: "skip can match empty input, so skipping it over and over would never end. Make it match at least one character, like `skip := WS` instead of `skip := WS*`".to_string(),
+                                pos_start,
+                                pos_end
+                            }
+                        )
+                    }
+                }
+            };
+            return Ok(())
         },
         _ => panic!("No, this should be a RulesNode, yo fcked up")
     }
 }
 
-pub fn run_interpreter(ast: nodes::Node, input: parserCombinators::Value) -> Result<parserCombinators::Value, error::Error> {
-    let mut context = context::Context{symbols: HashMap::new()};
-    add_def_pass(Box::new(ast.copy()), &mut context);
-    let main = match context.get("main".to_string()) {
+pub fn run_interpreter(ast: nodes::Node, input: &str) -> Result<usize, error::Error> {
+    return run_interpreter_rule(ast, "main", input)
+}
+
+pub fn definitions(ast: &nodes::Node) -> Result<context::Context, error::Error> {
+    let (pos_start, pos_end) = ast.get_pos();
+    let mut context = context::Context{symbols: HashMap::new(), dfas: HashMap::new(), pos_start, pos_end, options: context::MatchOptions::default()};
+    return match add_def_pass(Box::new(ast.copy()), &mut context) {
+        Ok(()) => Ok(context),
+        Err(err) => Err(err)
+    }
+}
+
+// Definitions plus a DFA for every definition that turns out to be regular. Uses
+// with the wrong number of arguments are turned down here, since a match that
+// backtracks past one would never say
+pub fn prepare(ast: &nodes::Node) -> Result<context::Context, error::Error> {
+    let mut context = match definitions(ast) {
+        Ok(a) => a,
+        Err(err) => return Err(err)
+    };
+    if let nodes::Node::RulesNode{rules, pos_start: _, pos_end: _} = ast {
+        for rule in rules {
+            if let nodes::Node::RuleNode{rhs: _, params, modifiers: _, lhs, pos_start: _, pos_end: _} = rule {
+                if let Err(err) = check_arity(lhs, &context, params) {
+                    return Err(err)
+                }
+            }
+        }
+    };
+    context.dfas = dfa::compile_regular(&context);
+    return Ok(context)
+}
+
+pub fn run_interpreter_rule(ast: nodes::Node, rule: &str, input: &str) -> Result<usize, error::Error> {
+    return run_interpreter_options(ast, rule, input, context::MatchOptions::default())
+}
+
+pub fn run_interpreter_options(ast: nodes::Node, rule: &str, input: &str, options: context::MatchOptions) -> Result<usize, error::Error> {
+    let mut context = match prepare(&ast) {
+        Ok(a) => a,
+        Err(err) => return Err(err)
+    };
+    context.options = options;
+    return run_context_rule(&context, rule, input, 0)
+}
+
+// Matches the rule against the input from byte `start` on and returns the byte the match ends at
+pub fn run_context_rule(context: &context::Context, rule: &str, input: &str, start: usize) -> Result<usize, error::Error> {
+    return run_context_rule_memo(context, rule, input, start, &mut Memo::new())
+}
+
+pub fn run_context_rule_memo(context: &context::Context, rule: &str, input: &str, start: usize, memo: &mut Memo) -> Result<usize, error::Error> {
+    let access = match entry_node(context, rule) {
+        Ok(a) => a,
+        Err(err) => return Err(err)
+    };
+    return match interpret_memo(&access, context, input, Ok((String::from(""), start)), memo) {
+        Ok((_, end)) => Ok(end),
+        Err(err) => Err(err)
+    }
+}
+
+// The tree of everything that matched inside the rule, which covers as much
+// of the input as the rule matched
+pub fn parse_context_rule(context: &context::Context, rule: &str, input: String) -> Result<tree::Tree, error::Error> {
+    let access = match entry_node(context, rule) {
+        Ok(a) => a,
+        Err(err) => return Err(err)
+    };
+    match interpret_tree(&access, context, &input, Ok((String::from(""), 0))) {
+        Ok(((_, end), mut trees)) => {
+            if !tree::silent(rule) {
+                if let Some(tree) = trees.pop() {
+                    return Ok(tree)
+                }
+            };
+            // A silent entry rule still gets a tree, to hold whatever matched inside it
+            Ok(tree::Tree{rule: rule.to_string(), start: 0, end, children: trees})
+        },
+        Err(err) => Err(err)
+    }
+}
+
+fn entry_node(context: &context::Context, rule: &str) -> Result<nodes::Node, error::Error> {
+    let entry = match context.get(rule.to_string()) {
         Some(a) => a,
         None => {
-            let (pos_start, pos_end) = ast.get_pos();
+            let (pos_start, pos_end) = context.pos();
             return Err (
                 error::Error{
                     name: "NoDefinitionError".to_string(),
-                    message: "No definition for main found".to_string(),
+                    message: format!("No definition for {} found", rule),
                     pos_start,
                     pos_end
                 }
             )
         }
     };
-    return interpret(Box::new(main.fun.copy()), &mut context, Ok(input))
+    if !entry.params.is_empty() {
+        let (pos_start, pos_end) = context.pos();
+        return Err(arity_error(&rule.to_string(), entry.params.len(), 0, pos_start, pos_end))
+    };
+    let (pos_start, pos_end) = entry.fun.get_pos();
+    return Ok(nodes::Node::RuleAccessNode{identifier: rule.to_string(), pos_start, pos_end})
 }
@@ -7,17 +7,19 @@ use crate::tokenTypes;
 #[derive(Debug)]
 pub struct Lexer {
     pub current_index: usize,
-    pub chars: Vec<u8>,
-    pub position: position::Position
+    pub chars: Vec<char>,
+    pub position: position::Position,
+    // Comments are left out of the tokens, but kept here for anything that wants them
+    pub comments: Vec<token::Token>
 }
 
 impl Lexer {
-    fn get_byte(&self) -> Option<&u8> {
+    fn get_byte(&self) -> Option<&char> {
         return self.chars.get(self.current_index)
     }
 
     fn get_char(&self) -> char {
-        let byte = if self.get_byte() == None { '\0' } else { self.chars[self.current_index] as char };
+        let byte = if self.get_byte() == None { '\0' } else { self.chars[self.current_index] };
         return byte as char
     }
 
@@ -39,6 +41,10 @@ impl Lexer {
         return self.get_char() == chararater;
     }
 
+    fn is_next_char(&self, chararater: char) -> bool {
+        return self.chars.get(self.current_index + 1usize) == Some(&chararater);
+    }
+
     fn make_token(&mut self, tok: tokenTypes::TokenTypes) -> token::Token {
         let pos_start = self.position.copy();
         self.advance();
@@ -84,6 +90,17 @@ impl Lexer {
         return token::Token{tok_type: Identifier, tok_value: chars, pos_start: pos_start, pos_end: self.position.copy()};
     }
 
+    // A `#` and the rest of its line, not including the newline
+    fn skip_comment(&mut self) {
+        let pos_start = self.position.copy();
+        let mut chars = String::from("");
+        while !(self.is_char('\n') || self.is_char('\0')) {
+            chars.push(self.get_char());
+            self.advance();
+        };
+        self.comments.push(token::Token{tok_type: Comment, tok_value: chars, pos_start, pos_end: self.position.copy()});
+    }
+
     fn errored_tok(&mut self) -> Result<Vec<token::Token>, error::Error> {
         let pos_start = self.position.copy();
         self.advance();
@@ -103,6 +120,21 @@ impl Lexer {
         return Ok(())
     }
 
+    fn make_caseless(&mut self) -> Vec<token::Token> {
+        self.advance();
+        let mut toks = if self.is_char('"') {
+            self.make_orstring()
+        } else {
+            vec![self.make_string(|this: &mut Lexer| this.get_char() == '\'')]
+        };
+        for tok in toks.iter_mut() {
+            if tok.tok_type == Str {
+                tok.tok_type = IStr
+            }
+        };
+        return toks;
+    }
+
     fn make_orstring(&mut self) -> Vec<token::Token> {
         let pos_start = self.position.copy();
         let mut toks = Vec::new();
@@ -127,6 +159,11 @@ impl Lexer {
             let current_char = self.get_char();
             match current_char {
                 _ if self.is_space() => self.advance(),
+                _ if self.is_char('i') && (self.is_next_char('\'') || self.is_next_char('"')) => {
+                    for tok in self.make_caseless() {
+                        tokens.push(tok)
+                    };
+                },
                 _ if self.is_ident() => tokens.push(self.make_ident()),
                 _ if self.is_char('\n') => tokens.push(self.make_token(Newline)),
                 _ if self.is_char('\'') => tokens.push(self.make_string(|this: &mut Lexer| this.get_char() == '\'')),
@@ -142,6 +179,10 @@ impl Lexer {
                 _ if self.is_char('*') => tokens.push(self.make_token(Asterisk)),
                 _ if self.is_char('+') => tokens.push(self.make_token(Plus)),
                 _ if self.is_char('?') => tokens.push(self.make_token(QuestionMark)),
+                _ if self.is_char('^') => tokens.push(self.make_token(Caret)),
+                _ if self.is_char('~') => tokens.push(self.make_token(Tilde)),
+                _ if self.is_char('@') => tokens.push(self.make_token(At)),
+                _ if self.is_char('#') => self.skip_comment(),
                 _ if self.is_char(':') =>
                     match self.two_char_tok(&mut tokens, '=') {
                         Ok(_) => (),
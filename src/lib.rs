@@ -0,0 +1,27 @@
+pub mod position;
+pub mod token;
+pub mod lexer;
+pub mod tokenTypes;
+pub mod parser;
+pub mod nodes;
+pub mod error;
+pub mod interpreter;
+pub mod rayRengine;
+pub mod context;
+pub mod parserCombinators;
+pub mod prelude;
+pub mod caseFolding;
+pub mod compiler;
+pub mod vm;
+pub mod dfa;
+pub mod analysis;
+pub mod prefilter;
+pub mod optimiser;
+pub mod grammarSet;
+pub mod matcher;
+pub mod executor;
+pub mod incremental;
+pub mod tree;
+pub mod actions;
+pub mod formatter;
+pub mod linter;
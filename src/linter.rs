@@ -0,0 +1,316 @@
+use std::collections::HashSet;
+use crate::nodes;
+use crate::context;
+use crate::position;
+use crate::prefilter;
+use crate::formatter;
+use crate::interpreter;
+use crate::analysis;
+use crate::prelude;
+use crate::tokenTypes::TokenTypes::*;
+
+// Something in a grammar that's probably not what was meant, with what to do
+// about it in the message
+pub struct Warning {
+    pub name: String,
+    pub rule: String,
+    pub message: String,
+    pub pos_start: position::Position,
+    pub pos_end: position::Position
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} in {}: {}\nFrom line no: {}, from coloumn no: {}", self.name, self.rule, self.message, self.pos_start.ln, self.pos_start.cn)
+    }
+}
+
+// Every warning for the rules in `ast`, rule by rule in the order they're written
+pub fn lint(ast: &nodes::Node) -> Vec<Warning> {
+    let rules = match ast {
+        nodes::Node::RulesNode{rules, pos_start: _, pos_end: _} => rules,
+        _ => return Vec::new()
+    };
+    // Rules are looked at the way they're written, but what a part of one
+    // matches is judged after `^` and `~` have rewritten it
+    let context = match interpreter::definitions(ast) {
+        Ok(a) => a,
+        Err(_) => return Vec::new()
+    };
+    let has_skip = context.get(String::from("skip")).is_some_and(|lazy| lazy.params.is_empty());
+    let used = used(rules);
+    let mut linter = Linter{context: &context, analysis: analysis::analyse(&context), warnings: Vec::new(), rule: String::new(), params: Vec::new(), caseless: false, skipping: false, pos: ast.get_pos()};
+    for rule in rules {
+        let (name, params, modifiers, body) = match rule {
+            nodes::Node::RuleNode{rhs, params, modifiers, lhs, pos_start: _, pos_end: _} => (rhs, params, modifiers, lhs),
+            _ => continue
+        };
+        linter.rule = name.clone();
+        linter.params = params.clone();
+        linter.caseless = modifiers.contains(&Caret);
+        linter.skipping = modifiers.contains(&Tilde) && has_skip && name != "skip";
+        linter.pos = rule.get_pos();
+        if let Some(used) = &used {
+            if !used.contains(name) {
+                linter.warn("UnusedRule", rule, format!("{} can't be reached from main, remove it or use it", name));
+            }
+        };
+        linter.parens(body);
+        linter.alternatives(body);
+    };
+    return linter.warnings
+}
+
+// The rules `main` uses, directly or not, or None when there isn't a `main`.
+// Rules with `~` use `skip` as well
+fn used(rules: &[nodes::Node]) -> Option<HashSet<String>> {
+    let find = |name: &String| rules.iter().rev().find(|rule| match rule {
+        nodes::Node::RuleNode{rhs, params: _, modifiers: _, lhs: _, pos_start: _, pos_end: _} => rhs == name,
+        _ => false
+    });
+    let main = String::from("main");
+    if find(&main).is_none() {
+        return None
+    };
+    let mut used = HashSet::new();
+    let mut pending = vec![main];
+    while let Some(name) = pending.pop() {
+        if used.contains(&name) {
+            continue
+        };
+        if let Some(nodes::Node::RuleNode{rhs: _, params, modifiers, lhs, pos_start: _, pos_end: _}) = find(&name) {
+            let mut names = Vec::new();
+            uses(lhs, &mut names);
+            pending.extend(names.into_iter().filter(|used| !params.contains(used)));
+            if modifiers.contains(&Tilde) {
+                pending.push(String::from("skip"));
+            }
+        };
+        used.insert(name);
+    };
+    return Some(used)
+}
+
+fn uses(node: &nodes::Node, names: &mut Vec<String>) {
+    match node {
+        nodes::Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => names.push(identifier.clone()),
+        nodes::Node::RuleCallNode{identifier, args, pos_start: _, pos_end: _} => {
+            names.push(identifier.clone());
+            args.iter().for_each(|arg| uses(arg, names));
+        },
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => chain.iter().for_each(|rule| uses(rule, names)),
+        nodes::Node::BinOpNode{left, op: _, right, pos_start: _, pos_end: _} => {
+            uses(left, names);
+            uses(right, names);
+        },
+        nodes::Node::PostFixNode{postfix, op: _, pos_start: _, pos_end: _} => uses(postfix, names),
+        _ => ()
+    }
+}
+
+struct Linter<'a> {
+    context: &'a context::Context,
+    analysis: analysis::Analysis<'a>,
+    warnings: Vec<Warning>,
+    rule: String,
+    params: Vec<String>,
+    // Whether the rule being looked at has `^` or `~`
+    caseless: bool,
+    skipping: bool,
+    pos: (position::Position, position::Position)
+}
+
+impl<'a> Linter<'a> {
+    fn warn(&mut self, name: &str, node: &nodes::Node, message: String) {
+        let (pos_start, pos_end) = match span(node) {
+            Some(a) => a,
+            None => (self.pos.0.copy(), self.pos.1.copy())
+        };
+        self.warnings.push(Warning{name: name.to_string(), rule: self.rule.clone(), message, pos_start, pos_end});
+    }
+
+    // Parenthesis aren't kept in the tree, but a chain inside a chain, or
+    // alternatives that are all of a chain, can only have come from them
+    fn parens(&mut self, node: &nodes::Node) {
+        match node {
+            nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => {
+                for rule in chain {
+                    let grouped = match rule {
+                        nodes::Node::RuleChainNode{chain: _, pos_start: _, pos_end: _} => true,
+                        // Alternatives that could be a set get told to be one instead
+                        nodes::Node::BinOpNode{left: _, op: _, right: _, pos_start: _, pos_end: _} => chain.len() == 1 && formatter::char_set(rule).is_none(),
+                        _ => false
+                    };
+                    if grouped && !written_as_set(rule) {
+                        self.warn("RedundantParentheses", rule, format!("the parentheses around {} aren't needed", formatter::show(rule, formatter::ANYTHING)));
+                    };
+                    self.parens(rule);
+                }
+            },
+            nodes::Node::PostFixNode{postfix, op, pos_start: _, pos_end: _} => {
+                if let nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} = &**postfix {
+                    let atom = chain.len() == 1 && matches!(&chain[0], nodes::Node::StrNode{string: _, caseless: _, pos_start: _, pos_end: _} | nodes::Node::RuleAccessNode{identifier: _, pos_start: _, pos_end: _} | nodes::Node::RuleCallNode{identifier: _, args: _, pos_start: _, pos_end: _});
+                    if atom && !written_as_set(postfix) && formatter::show(&chain[0], formatter::ATOM) == formatter::show(&chain[0], formatter::ANYTHING) {
+                        self.warn("RedundantParentheses", postfix, format!("the parentheses around {} aren't needed, write {}{}", formatter::show(postfix, formatter::ANYTHING), formatter::show(postfix, formatter::ATOM), nodes::operator_symbol(*op)));
+                    }
+                };
+                self.parens(postfix);
+            },
+            nodes::Node::BinOpNode{left, op: _, right, pos_start: _, pos_end: _} => {
+                if let Some(set) = formatter::char_set(node) {
+                    if !written_as_set(node) {
+                        self.warn("UseCharacterSet", node, format!("{} is the same as {}", flatten(node).iter().map(|alternative| formatter::show(alternative, formatter::SEQUENCE)).collect::<Vec<String>>().join(" | "), set));
+                    };
+                    return
+                };
+                self.parens(left);
+                // `|` groups to the left, so alternatives on the right need theirs
+                match &**right {
+                    nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} if chain.len() == 1 && matches!(&chain[0], nodes::Node::BinOpNode{left: _, op: _, right: _, pos_start: _, pos_end: _}) => self.parens(&chain[0]),
+                    _ => self.parens(right)
+                }
+            },
+            nodes::Node::RuleCallNode{identifier: _, args, pos_start: _, pos_end: _} => args.iter().for_each(|arg| self.parens(arg)),
+            _ => ()
+        }
+    }
+
+    fn alternatives(&mut self, node: &nodes::Node) {
+        match node {
+            nodes::Node::BinOpNode{left: _, op: _, right: _, pos_start: _, pos_end: _} => {
+                let alternatives = flatten(node);
+                // Parameters could stand for anything, so rules that use them aren't judged
+                if !alternatives.iter().any(|alternative| self.mentions_param(alternative)) {
+                    self.unreachable(&alternatives);
+                };
+                alternatives.iter().for_each(|alternative| self.alternatives(alternative));
+            },
+            nodes::Node::PostFixNode{postfix, op, pos_start: _, pos_end: _} => {
+                if *op != Plus && !self.mentions_param(postfix) && self.can_not_fail(postfix) {
+                    self.warn("RedundantOptional", node, format!("{} always succeeds already, so the {} does nothing", formatter::show(postfix, formatter::ANYTHING), nodes::operator_symbol(*op)));
+                };
+                self.alternatives(postfix);
+            },
+            nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => chain.iter().for_each(|rule| self.alternatives(rule)),
+            nodes::Node::RuleCallNode{identifier: _, args, pos_start: _, pos_end: _} => args.iter().for_each(|arg| self.alternatives(arg)),
+            _ => ()
+        }
+    }
+
+    // An alternative is never the one that matches when one before it can't
+    // fail, or only ever matches a string every match of it starts with
+    fn unreachable(&mut self, alternatives: &[&nodes::Node]) {
+        let mut reported = Vec::new();
+        for (j, later) in alternatives.iter().enumerate() {
+            let prefix = prefilter::prefix(self.context, &self.matched(later));
+            for earlier in alternatives[..j].iter() {
+                let shadows = match prefilter::exact(self.context, &self.matched(earlier)) {
+                    Some(exact) => prefix.starts_with(&exact),
+                    None => self.can_not_fail(earlier)
+                };
+                if shadows && !reported.contains(&j) {
+                    reported.push(j);
+                    let (earlier, later) = (formatter::show(earlier, formatter::SEQUENCE), formatter::show(later, formatter::SEQUENCE));
+                    self.warn("UnreachableAlternative", alternatives[j], format!("{} never gets a chance, {} before it matches first; put {} first", later, earlier, later));
+                }
+            }
+        }
+    }
+
+    fn mentions_param(&self, node: &nodes::Node) -> bool {
+        let mut names = Vec::new();
+        uses(node, &mut names);
+        return names.iter().any(|name| self.params.contains(name))
+    }
+
+    // What part of the rule being looked at is actually matched as
+    fn matched(&self, node: &nodes::Node) -> nodes::Node {
+        let node = if self.caseless { node.caseless() } else { node.copy() };
+        return if self.skipping { node.syntactic("skip") } else { node }
+    }
+
+    // Whether the node succeeds whatever the input is. Unless something in it
+    // looks at the input without consuming it, that's the same as being able to
+    // match nothing
+    fn can_not_fail(&self, node: &nodes::Node) -> bool {
+        let node = self.matched(node);
+        return self.analysis.first(&node).nullable && !looks_ahead(&node, self.context, &mut Vec::new())
+    }
+}
+
+// Every alternative of a choice, however its `|`s are grouped
+fn flatten(node: &nodes::Node) -> Vec<&nodes::Node> {
+    match node {
+        nodes::Node::BinOpNode{left, op: Pipe, right, pos_start: _, pos_end: _} => {
+            let mut alternatives = flatten(left);
+            alternatives.extend(flatten(right));
+            alternatives
+        },
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} if chain.len() == 1 && matches!(&chain[0], nodes::Node::BinOpNode{left: _, op: _, right: _, pos_start: _, pos_end: _}) => flatten(&chain[0]),
+        _ => vec![node]
+    }
+}
+
+// Whether matching the node can get to `EOI`, or to anything the analysis
+// doesn't look into, it's fine to say yes when it's not sure
+fn looks_ahead(node: &nodes::Node, context: &context::Context, visiting: &mut Vec<String>) -> bool {
+    match node {
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => chain.iter().any(|rule| looks_ahead(rule, context, visiting)),
+        nodes::Node::BinOpNode{left, op: _, right, pos_start: _, pos_end: _} => looks_ahead(left, context, visiting) || looks_ahead(right, context, visiting),
+        nodes::Node::PostFixNode{postfix, op: _, pos_start: _, pos_end: _} => looks_ahead(postfix, context, visiting),
+        nodes::Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => match context.symbols.get(identifier) {
+            Some(_) if visiting.contains(identifier) => false,
+            Some(lazy) if lazy.params.is_empty() => {
+                visiting.push(identifier.clone());
+                let res = looks_ahead(&lazy.fun, context, visiting);
+                visiting.pop();
+                res
+            },
+            Some(_) => true,
+            None => matches!(prelude::lookup(identifier), Some(prelude::Builtin::EndOfInput))
+        },
+        nodes::Node::StrNode{string: _, caseless: _, pos_start: _, pos_end: _} => false,
+        _ => true
+    }
+}
+
+// Double quoted sets turn into alternatives in parenthesis as they're lexed,
+// the only way to tell is to look at what the first of them was written with
+fn written_as_set(node: &nodes::Node) -> bool {
+    let (pos_start, _) = match span(node) {
+        Some(a) => a,
+        None => return false
+    };
+    return pos_start.ftext.chars().nth(pos_start.index as usize) == Some('"')
+}
+
+// From the first thing in the node that knows where it was written to the
+// last, chains and alternatives themselves don't
+fn span(node: &nodes::Node) -> Option<(position::Position, position::Position)> {
+    match (first_leaf(node), last_leaf(node)) {
+        (Some(first), Some(last)) => {
+            let (pos_start, _) = first.get_pos();
+            let (_, pos_end) = last.get_pos();
+            Some((pos_start, pos_end))
+        },
+        _ => None
+    }
+}
+
+fn first_leaf(node: &nodes::Node) -> Option<&nodes::Node> {
+    match node {
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => chain.first().and_then(first_leaf),
+        nodes::Node::BinOpNode{left, op: _, right: _, pos_start: _, pos_end: _} => first_leaf(left),
+        nodes::Node::PostFixNode{postfix, op: _, pos_start: _, pos_end: _} => first_leaf(postfix),
+        _ => Some(node)
+    }
+}
+
+fn last_leaf(node: &nodes::Node) -> Option<&nodes::Node> {
+    match node {
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => chain.last().and_then(last_leaf),
+        nodes::Node::BinOpNode{left: _, op: _, right, pos_start: _, pos_end: _} => last_leaf(right),
+        nodes::Node::PostFixNode{postfix, op: _, pos_start: _, pos_end: _} => last_leaf(postfix),
+        _ => Some(node)
+    }
+}
@@ -1,26 +1,14 @@
-mod position;
-mod token;
-mod lexer;
-mod tokenTypes;
-mod parser;
-mod nodes;
-mod error;
-mod interpreter;
-mod rayRengine;
-mod context;
-mod parserCombinators;
-use tokenTypes::TokenTypes::*;
+use ray_rengine::rayRengine;
 
 fn main() {
     let regex =
         String::from(
             "
-            nums := \"1234567890\"
-            whitespace := '\t' | ' ' | '\n'
-            alphabets := \"abcdefghijklmnopqrstuvwxyz\"
-            expr := term (whitespace* ('+'|'-') whitespace* term)*
-            factor := '(' whitespace* expr whitespace* ')' | nums+
-            term := factor (whitespace* ('*'|'/') whitespace* factor)*
+            skip := WS
+            nums := DIGIT+
+            ~expr := term (('+'|'-') term)*
+            ~factor := '(' expr ')' | nums
+            ~term := factor (('*'|'/') factor)*
             main := expr
             "
         );
@@ -0,0 +1,101 @@
+use crate::compiler;
+use crate::error;
+use crate::vm;
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Status {
+    // The rule is in the middle of matching at the end of what's been fed
+    NeedMoreInput,
+    // Everything fed so far matches, feeding anything more undoes that
+    Matches,
+    // Nothing that's fed from here on can make it match
+    Rejected
+}
+
+// Matches a rule against input that comes in chunks, without keeping more of it
+// around than the match can still backtrack into
+pub struct Matcher {
+    program: compiler::Program,
+    machine: vm::Machine,
+    // The input from byte `base` on, chunks are cut wherever they like so up to
+    // the last few bytes of what's been fed wait in `pending` for the rest of their character
+    text: String,
+    pending: Vec<u8>,
+    base: usize,
+    total: usize,
+    result: Option<Result<usize, error::Error>>
+}
+
+impl Matcher {
+    pub fn new(program: compiler::Program, entry: usize) -> Matcher {
+        return Matcher{program, machine: vm::Machine::new(entry), text: String::new(), pending: Vec::new(), base: 0, total: 0, result: None}
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Status, error::Error> {
+        self.pending.extend_from_slice(chunk);
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(a) => a.len(),
+            Err(err) => match err.error_len() {
+                Some(_) => {
+                    let err = self.encoding_error(self.total + err.valid_up_to());
+                    self.result = Some(Err(err.copy()));
+                    return Err(err)
+                },
+                None => err.valid_up_to()
+            }
+        };
+        if self.result.is_none() {
+            self.text.push_str(std::str::from_utf8(&self.pending[..valid]).unwrap_or_default());
+        };
+        self.pending.drain(..valid);
+        self.total += valid;
+        return self.advance(false)
+    }
+
+    // How many bytes of input are being held on to
+    pub fn buffered(&self) -> usize {
+        return self.text.len() + self.pending.len()
+    }
+
+    // Whether everything that was fed matches the rule
+    pub fn finish(mut self) -> Result<bool, error::Error> {
+        if !self.pending.is_empty() && self.result.is_none() {
+            return Err(self.encoding_error(self.total))
+        };
+        match self.advance(true) {
+            Ok(status) => Ok(status == Status::Matches),
+            Err(err) => Err(err)
+        }
+    }
+
+    fn advance(&mut self, finished: bool) -> Result<Status, error::Error> {
+        if self.result.is_none() {
+            match self.machine.resume(&self.program, &self.text, self.base, finished) {
+                vm::Outcome::Done(res) => self.result = Some(res),
+                vm::Outcome::NeedMoreInput => {
+                    let oldest = self.machine.oldest();
+                    self.text.drain(..oldest - self.base);
+                    self.base = oldest;
+                    return Ok(Status::NeedMoreInput)
+                }
+            };
+            // The match is decided, all that's left to know is how much input there is
+            self.text = String::new();
+            self.base = self.total;
+        };
+        match &self.result {
+            Some(Ok(end)) => Ok(if *end == self.total && self.pending.is_empty() { Status::Matches } else { Status::Rejected }),
+            Some(Err(err)) => if err.name == "InputError" { Ok(Status::Rejected) } else { Err(err.copy()) },
+            None => Ok(Status::NeedMoreInput)
+        }
+    }
+
+    fn encoding_error(&self, at: usize) -> error::Error {
+        return error::Error{
+            name: "EncodingError".to_string(),
+            message: format!("Input isn't valid UTF-8 at byte {}", at),
+            pos_start: self.program.pos_start.copy(),
+            pos_end: self.program.pos_end.copy()
+        }
+    }
+}
@@ -1,59 +1,227 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use crate::tokenTypes;
 use crate::token;
 use crate::position;
 
 #[derive(Debug)]
 pub enum Node {
-    StrNode{string: String, pos_start: position::Position, pos_end: position::Position},
+    StrNode{string: String, caseless: bool, pos_start: position::Position, pos_end: position::Position},
     BinOpNode{left: Box<Node>, op: tokenTypes::TokenTypes, right: Box<Node>, pos_start: position::Position, pos_end: position::Position},
     RulesNode{rules: Vec<Node>, pos_start: position::Position, pos_end: position::Position},
-    RuleNode{rhs: String, lhs: Box<Node>, pos_start: position::Position, pos_end: position::Position},
+    RuleNode{rhs: String, params: Vec<String>, modifiers: Vec<tokenTypes::TokenTypes>, lhs: Box<Node>, pos_start: position::Position, pos_end: position::Position},
     PostFixNode{postfix: Box<Node>, op: tokenTypes::TokenTypes, pos_start: position::Position, pos_end: position::Position},
     RuleAccessNode{identifier: String, pos_start: position::Position, pos_end: position::Position},
-    RuleChainNode{chain: Vec<Node>, pos_start: position::Position, pos_end: position::Position}
+    RuleChainNode{chain: Vec<Node>, pos_start: position::Position, pos_end: position::Position},
+    RuleCallNode{identifier: String, args: Vec<Node>, pos_start: position::Position, pos_end: position::Position}
 }
 
 impl Node {
     pub fn get_pos(&self) -> (position::Position, position::Position) {
         match self {
-            Node::StrNode{string: _, pos_start, pos_end} => (pos_start.copy(), pos_end.copy()),
+            Node::StrNode{string: _, caseless: _, pos_start, pos_end} => (pos_start.copy(), pos_end.copy()),
             Node::RulesNode{rules: _, pos_start, pos_end} => (pos_start.copy(), pos_end.copy()),
-            Node::RuleNode{rhs: _, lhs: _, pos_start, pos_end} => (pos_start.copy(), pos_end.copy()),
+            Node::RuleNode{rhs: _, params: _, modifiers: _, lhs: _, pos_start, pos_end} => (pos_start.copy(), pos_end.copy()),
             Node::BinOpNode{left: _, op: _, right: _, pos_start, pos_end} => (pos_start.copy(), pos_end.copy()),
             Node::PostFixNode{postfix: _, op: _, pos_start, pos_end} => (pos_start.copy(), pos_end.copy()),
             Node::RuleAccessNode{identifier: _, pos_start, pos_end} => (pos_start.copy(), pos_end.copy()),
-            Node::RuleChainNode{chain: _, pos_start, pos_end} => (pos_start.copy(), pos_end.copy())
+            Node::RuleChainNode{chain: _, pos_start, pos_end} => (pos_start.copy(), pos_end.copy()),
+            Node::RuleCallNode{identifier: _, args: _, pos_start, pos_end} => (pos_start.copy(), pos_end.copy())
         }
     }
 
     pub fn copy(&self) -> Node {
         match self {
-            Node::StrNode{string, pos_start, pos_end} => Node::StrNode{string: string.clone(), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            Node::StrNode{string, caseless, pos_start, pos_end} => Node::StrNode{string: string.clone(), caseless: *caseless, pos_start: pos_start.copy(), pos_end: pos_end.copy()},
             Node::RulesNode{rules, pos_start, pos_end} => Node::RulesNode{rules: rules.into_iter().map(|x| x.copy()).collect(), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
-            Node::RuleNode{rhs, lhs, pos_start, pos_end} => Node::RuleNode{rhs: rhs.clone(), lhs: Box::new(lhs.copy()), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            Node::RuleNode{rhs, params, modifiers, lhs, pos_start, pos_end} => Node::RuleNode{rhs: rhs.clone(), params: params.clone(), modifiers: modifiers.clone(), lhs: Box::new(lhs.copy()), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
             Node::BinOpNode{left, op, right, pos_start, pos_end} =>
                 Node::BinOpNode{left: Box::new(left.copy()), op: *op, right: Box::new(right.copy()), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
             Node::PostFixNode{postfix, op, pos_start, pos_end} => Node::PostFixNode{postfix: Box::new(postfix.copy()), op: *op, pos_start: pos_start.copy(), pos_end: pos_end.copy()},
             Node::RuleAccessNode{identifier, pos_start, pos_end} => Node::RuleAccessNode{identifier: identifier.clone(), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
-            Node::RuleChainNode{chain, pos_start, pos_end} => Node::RuleChainNode{chain: chain.into_iter().map(|x| x.copy()).collect(), pos_start: pos_start.copy(), pos_end: pos_end.copy()}
+            Node::RuleChainNode{chain, pos_start, pos_end} => Node::RuleChainNode{chain: chain.into_iter().map(|x| x.copy()).collect(), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            Node::RuleCallNode{identifier, args, pos_start, pos_end} => Node::RuleCallNode{identifier: identifier.clone(), args: args.into_iter().map(|x| x.copy()).collect(), pos_start: pos_start.copy(), pos_end: pos_end.copy()}
         }
     }
+
+    // What a chain of just one thing stands for. Arguments are parsed as chains,
+    // so passing one on as it is would otherwise wrap it in one more every time
+    pub fn unwrapped(&self) -> &Node {
+        match self {
+            Node::RuleChainNode{chain, pos_start: _, pos_end: _} if chain.len() == 1 => chain[0].unwrapped(),
+            _ => self
+        }
+    }
+
+    // Copies the node while replacing every access to one of `params` with the
+    // argument bound to it, this is how a parameterised rule gets expanded at its call site
+    pub fn bind(&self, params: &Vec<String>, args: &Vec<Node>) -> Node {
+        match self {
+            Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => {
+                match params.iter().position(|param| param == identifier) {
+                    Some(i) => args[i].unwrapped().copy(),
+                    None => self.copy()
+                }
+            },
+            Node::BinOpNode{left, op, right, pos_start, pos_end} =>
+                Node::BinOpNode{left: Box::new(left.bind(params, args)), op: *op, right: Box::new(right.bind(params, args)), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            Node::PostFixNode{postfix, op, pos_start, pos_end} => Node::PostFixNode{postfix: Box::new(postfix.bind(params, args)), op: *op, pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            Node::RuleChainNode{chain, pos_start, pos_end} => Node::RuleChainNode{chain: chain.iter().map(|x| x.bind(params, args)).collect(), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            Node::RuleCallNode{identifier, args: call_args, pos_start, pos_end} =>
+                Node::RuleCallNode{identifier: identifier.clone(), args: call_args.iter().map(|x| x.bind(params, args)).collect(), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            _ => self.copy()
+        }
+    }
+
+    // Reads `t(x)` the way it was read before definitions could have parameters, as
+    // `t` followed by `(x)`, wherever `t` isn't one of the `parameterised` definitions
+    pub fn chain_calls(&self, parameterised: &HashSet<String>) -> Node {
+        match self {
+            Node::RulesNode{rules, pos_start, pos_end} => Node::RulesNode{rules: rules.iter().map(|x| x.chain_calls(parameterised)).collect(), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            Node::RuleNode{rhs, params, modifiers, lhs, pos_start, pos_end} =>
+                Node::RuleNode{rhs: rhs.clone(), params: params.clone(), modifiers: modifiers.clone(), lhs: Box::new(lhs.chain_calls(parameterised)), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            Node::RuleChainNode{chain, pos_start, pos_end} => {
+                let mut chained = Vec::new();
+                for rule in chain {
+                    match split_call(rule, parameterised) {
+                        Some((access, rest)) => {
+                            chained.push(access);
+                            chained.push(rest);
+                        },
+                        None => chained.push(rule.chain_calls(parameterised))
+                    }
+                };
+                Node::RuleChainNode{chain: chained, pos_start: pos_start.copy(), pos_end: pos_end.copy()}
+            },
+            Node::BinOpNode{left, op, right, pos_start, pos_end} =>
+                Node::BinOpNode{left: Box::new(left.chain_calls(parameterised)), op: *op, right: Box::new(right.chain_calls(parameterised)), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            Node::PostFixNode{postfix, op, pos_start, pos_end} => Node::PostFixNode{postfix: Box::new(postfix.chain_calls(parameterised)), op: *op, pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            Node::RuleCallNode{identifier, args, pos_start, pos_end} =>
+                Node::RuleCallNode{identifier: identifier.clone(), args: args.iter().map(|x| x.chain_calls(parameterised)).collect(), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            _ => self.copy()
+        }
+    }
+
+    pub fn caseless(&self) -> Node {
+        match self {
+            Node::StrNode{string, caseless: _, pos_start, pos_end} => Node::StrNode{string: string.clone(), caseless: true, pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            Node::BinOpNode{left, op, right, pos_start, pos_end} =>
+                Node::BinOpNode{left: Box::new(left.caseless()), op: *op, right: Box::new(right.caseless()), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            Node::PostFixNode{postfix, op, pos_start, pos_end} => Node::PostFixNode{postfix: Box::new(postfix.caseless()), op: *op, pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            Node::RuleChainNode{chain, pos_start, pos_end} => Node::RuleChainNode{chain: chain.iter().map(|x| x.caseless()).collect(), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            Node::RuleCallNode{identifier, args, pos_start, pos_end} =>
+                Node::RuleCallNode{identifier: identifier.clone(), args: args.iter().map(|x| x.caseless()).collect(), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            _ => self.copy()
+        }
+    }
+
+    // Puts `skip*` between the elements of every chain and between the repetitions
+    // of every `*` and `+`, this is what makes a `~` definition whitespace insensitive
+    pub fn syntactic(&self, skip: &str) -> Node {
+        match self {
+            Node::RuleChainNode{chain, pos_start, pos_end} => {
+                let mut skipping = Vec::new();
+                for rule in chain {
+                    if !skipping.is_empty() {
+                        skipping.push(skip_node(skip, rule));
+                    };
+                    skipping.push(rule.syntactic(skip));
+                };
+                Node::RuleChainNode{chain: skipping, pos_start: pos_start.copy(), pos_end: pos_end.copy()}
+            },
+            Node::PostFixNode{postfix, op, pos_start, pos_end} if *op != tokenTypes::TokenTypes::QuestionMark => {
+                let postfix = postfix.syntactic(skip);
+                let rest = Node::PostFixNode{
+                    postfix: Box::new(Node::RuleChainNode{chain: vec![skip_node(skip, &postfix), postfix.copy()], pos_start: pos_start.copy(), pos_end: pos_end.copy()}),
+                    op: tokenTypes::TokenTypes::Asterisk,
+                    pos_start: pos_start.copy(),
+                    pos_end: pos_end.copy()
+                };
+                let repeated = Node::RuleChainNode{chain: vec![postfix, rest], pos_start: pos_start.copy(), pos_end: pos_end.copy()};
+                if *op == tokenTypes::TokenTypes::Plus {
+                    repeated
+                } else {
+                    Node::PostFixNode{postfix: Box::new(repeated), op: tokenTypes::TokenTypes::QuestionMark, pos_start: pos_start.copy(), pos_end: pos_end.copy()}
+                }
+            },
+            Node::PostFixNode{postfix, op, pos_start, pos_end} => Node::PostFixNode{postfix: Box::new(postfix.syntactic(skip)), op: *op, pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            Node::BinOpNode{left, op, right, pos_start, pos_end} =>
+                Node::BinOpNode{left: Box::new(left.syntactic(skip)), op: *op, right: Box::new(right.syntactic(skip)), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            Node::RuleCallNode{identifier, args, pos_start, pos_end} =>
+                Node::RuleCallNode{identifier: identifier.clone(), args: args.iter().map(|x| x.syntactic(skip)).collect(), pos_start: pos_start.copy(), pos_end: pos_end.copy()},
+            _ => self.copy()
+        }
+    }
+}
+
+// A call with one argument to something that takes none, split into the access and
+// what follows it. A postfix operator after the call only goes with the argument
+fn split_call(node: &Node, parameterised: &HashSet<String>) -> Option<(Node, Node)> {
+    match node {
+        Node::RuleCallNode{identifier, args, pos_start, pos_end: _} if args.len() == 1 && !parameterised.contains(identifier) => {
+            let mut end = pos_start.copy();
+            identifier.chars().for_each(|c| { end.advance(c); });
+            let access = Node::RuleAccessNode{identifier: identifier.clone(), pos_start: pos_start.copy(), pos_end: end};
+            Some((access, args[0].chain_calls(parameterised)))
+        },
+        Node::PostFixNode{postfix, op, pos_start: _, pos_end} => match split_call(postfix, parameterised) {
+            Some((access, rest)) => {
+                let (pos_start, _) = rest.get_pos();
+                Some((access, Node::PostFixNode{postfix: Box::new(rest), op: *op, pos_start, pos_end: pos_end.copy()}))
+            },
+            None => None
+        },
+        _ => None
+    }
+}
+
+fn skip_node(skip: &str, near: &Node) -> Node {
+    let (pos_start, pos_end) = near.get_pos();
+    let access = Node::RuleAccessNode{identifier: skip.to_string(), pos_start: pos_start.copy(), pos_end: pos_end.copy()};
+    return Node::PostFixNode{postfix: Box::new(access), op: tokenTypes::TokenTypes::Asterisk, pos_start, pos_end}
+}
+
+pub fn operator_symbol(op: tokenTypes::TokenTypes) -> &'static str {
+    match op {
+        tokenTypes::TokenTypes::Pipe => "|",
+        tokenTypes::TokenTypes::Asterisk => "*",
+        tokenTypes::TokenTypes::Plus => "+",
+        tokenTypes::TokenTypes::QuestionMark => "?",
+        _ => ""
+    }
+}
+
+pub fn modifier_symbol(modifier: tokenTypes::TokenTypes) -> &'static str {
+    match modifier {
+        tokenTypes::TokenTypes::Caret => "^",
+        tokenTypes::TokenTypes::Tilde => "~",
+        tokenTypes::TokenTypes::At => "@",
+        _ => ""
+    }
 }
 
 impl std::fmt::Display for Node {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            Node::StrNode{string, pos_start: _, pos_end: _} => write!(f, "{}", string),
+            Node::StrNode{string, caseless, pos_start: _, pos_end: _} => if *caseless { write!(f, "i'{}'", string) } else { write!(f, "{}", string) },
             Node::RulesNode{rules, pos_start: _, pos_end: _} => {
                 write!(f, "{}{}{}", "[", rules.iter().map(|n| format!("{}", n)).collect::<Vec<String>>().join(", "), "]")
             },
             Node::BinOpNode{left, op, right, pos_start: _, pos_end: _} => write!(f, "({} {} {})", left, op, right),
-            Node::RuleNode{rhs, lhs, pos_start: _, pos_end: _} => write!(f, "{} := {}", rhs, lhs),
+            Node::RuleNode{rhs, params, modifiers, lhs, pos_start: _, pos_end: _} => {
+                let modifiers = modifiers.iter().map(|m| modifier_symbol(*m)).collect::<Vec<&str>>().join("");
+                if params.is_empty() {
+                    write!(f, "{}{} := {}", modifiers, rhs, lhs)
+                } else {
+                    write!(f, "{}{}({}) := {}", modifiers, rhs, params.join(", "), lhs)
+                }
+            },
             Node::PostFixNode{postfix, op, pos_start: _, pos_end: _} => write!(f, "({} {})", postfix, op),
             Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => write!(f, "{}", identifier),
             Node::RuleChainNode{chain, pos_start: _, pos_end: _} => {
                 write!(f, "{}{}{}", "(", chain.iter().map(|n| format!("{}", n)).collect::<Vec<String>>().join(" "), ")")
+            },
+            Node::RuleCallNode{identifier, args, pos_start: _, pos_end: _} => {
+                write!(f, "{}({})", identifier, args.iter().map(|n| format!("{}", n)).collect::<Vec<String>>().join(", "))
             }
         }
     }
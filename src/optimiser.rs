@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+use crate::nodes;
+use crate::context;
+use crate::position;
+use crate::tree;
+use crate::tokenTypes::TokenTypes::*;
+
+// Silent definitions this big or smaller, counted in nodes after their own uses
+// were inlined, get written out in place of every use
+const INLINE_SIZE: usize = 16;
+
+// How many times each rewrite fired
+#[derive(Debug, Default)]
+pub struct Report {
+    pub inlined: usize,
+    pub flattened: usize,
+    pub merged: usize,
+    pub factored: usize
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "inlined {} uses, flattened {} chains, merged {} literals, factored {} choices", self.inlined, self.flattened, self.merged, self.factored)
+    }
+}
+
+// Rewrites every definition into one that matches the same inputs and leaves
+// the same input behind, though errors talk about the rewritten definitions
+pub fn optimise(context: &mut context::Context) -> Report {
+    let mut optimiser = Optimiser{context, report: Report::default(), expanded: HashMap::new(), visiting: Vec::new()};
+    let mut names: Vec<String> = optimiser.context.symbols.keys().cloned().collect();
+    names.sort();
+    let mut bodies = Vec::new();
+    for name in names.iter() {
+        let lazy = &optimiser.context.symbols[name];
+        let body = lazy.fun.copy();
+        let params = lazy.params.clone();
+        optimiser.visiting.push(name.clone());
+        let body = optimiser.inline(body, &params, name);
+        optimiser.visiting.pop();
+        bodies.push(optimiser.simplify(body));
+    };
+    let report = optimiser.report;
+    for (name, body) in names.iter().zip(bodies) {
+        if let Some(lazy) = context.symbols.get_mut(name) {
+            *lazy.fun = body;
+        }
+    };
+    return report
+}
+
+// Every definition written out the way it's matched now
+pub fn dump(context: &context::Context) -> String {
+    let mut names: Vec<&String> = context.symbols.keys().collect();
+    names.sort();
+    return names.iter().map(|name| {
+        let lazy = &context.symbols[*name];
+        if lazy.params.is_empty() {
+            format!("{} := {}\n", name, show(&lazy.fun, false))
+        } else {
+            format!("{}({}) := {}\n", name, lazy.params.join(", "), show(&lazy.fun, false))
+        }
+    }).collect()
+}
+
+fn show(node: &nodes::Node, nested: bool) -> String {
+    let shown = match node {
+        nodes::Node::StrNode{string, caseless, pos_start: _, pos_end: _} => return format!("{}'{}'", if *caseless { "i" } else { "" }, string),
+        nodes::Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => return identifier.clone(),
+        nodes::Node::RuleCallNode{identifier, args, pos_start: _, pos_end: _} =>
+            return format!("{}({})", identifier, args.iter().map(|arg| show(arg, false)).collect::<Vec<String>>().join(", ")),
+        nodes::Node::PostFixNode{postfix, op, pos_start: _, pos_end: _} => return format!("{}{}", show(postfix, true), nodes::operator_symbol(*op)),
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} if chain.is_empty() => return String::from("()"),
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} if chain.len() == 1 => return show(&chain[0], nested),
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => chain.iter().map(|rule| show(rule, true)).collect::<Vec<String>>().join(" "),
+        nodes::Node::BinOpNode{left, op, right, pos_start: _, pos_end: _} => {
+            let mut alternatives = Vec::new();
+            alternatives_of(node, &mut alternatives);
+            if alternatives.len() < 2 {
+                format!("{} {} {}", show(left, true), nodes::operator_symbol(*op), show(right, true))
+            } else {
+                alternatives.iter().map(|alternative| show(alternative, false)).collect::<Vec<String>>().join(" | ")
+            }
+        },
+        _ => format!("{}", node)
+    };
+    return if nested { format!("({})", shown) } else { shown }
+}
+
+struct Optimiser<'a> {
+    context: &'a context::Context,
+    report: Report,
+    expanded: HashMap<String, Option<nodes::Node>>,
+    visiting: Vec<String>
+}
+
+impl<'a> Optimiser<'a> {
+    // What a parameterless definition turns into with its own uses inlined,
+    // None if it uses itself or ends up too big. Only silent ones that aren't
+    // atomic, anything else puts a node in the parse tree that'd go missing
+    fn expansion(&mut self, name: &String) -> Option<nodes::Node> {
+        if let Some(known) = self.expanded.get(name) {
+            return known.as_ref().map(|body| body.copy())
+        };
+        let lazy = match self.context.symbols.get(name) {
+            Some(lazy) if lazy.params.is_empty() && tree::silent(name) && !lazy.atomic && !self.visiting.contains(name) => lazy,
+            _ => return None
+        };
+        self.visiting.push(name.clone());
+        let body = self.inline(lazy.fun.copy(), &Vec::new(), name);
+        self.visiting.pop();
+        let res = if size(&body) <= INLINE_SIZE && !uses(&body, name) { Some(body) } else { None };
+        self.expanded.insert(name.clone(), res.as_ref().map(|body| body.copy()));
+        return res
+    }
+
+    fn inline(&mut self, node: nodes::Node, params: &Vec<String>, within: &String) -> nodes::Node {
+        match node {
+            nodes::Node::RuleAccessNode{identifier, pos_start, pos_end} => {
+                // A definition with its own automaton is quicker left as it is,
+                // unless what it's written into gets one as well
+                let keep = params.contains(&identifier) || (self.context.dfas.contains_key(&identifier) && !self.context.dfas.contains_key(within));
+                match if keep { None } else { self.expansion(&identifier) } {
+                    // Names in the definition that happen to be parameters here
+                    // would get bound to the arguments once it's written out
+                    Some(body) if params.iter().any(|param| uses(&body, param)) => nodes::Node::RuleAccessNode{identifier, pos_start, pos_end},
+                    Some(body) => {
+                        self.report.inlined += 1;
+                        body
+                    },
+                    None => nodes::Node::RuleAccessNode{identifier, pos_start, pos_end}
+                }
+            },
+            nodes::Node::RuleChainNode{chain, pos_start, pos_end} =>
+                nodes::Node::RuleChainNode{chain: chain.into_iter().map(|rule| self.inline(rule, params, within)).collect(), pos_start, pos_end},
+            nodes::Node::BinOpNode{left, op, right, pos_start, pos_end} => {
+                let left = Box::new(self.inline(*left, params, within));
+                let right = Box::new(self.inline(*right, params, within));
+                nodes::Node::BinOpNode{left, op, right, pos_start, pos_end}
+            },
+            nodes::Node::PostFixNode{postfix, op, pos_start, pos_end} =>
+                nodes::Node::PostFixNode{postfix: Box::new(self.inline(*postfix, params, within)), op, pos_start, pos_end},
+            nodes::Node::RuleCallNode{identifier, args, pos_start, pos_end} =>
+                nodes::Node::RuleCallNode{identifier, args: args.into_iter().map(|arg| self.inline(arg, params, within)).collect(), pos_start, pos_end},
+            other => other
+        }
+    }
+
+    fn simplify(&mut self, node: nodes::Node) -> nodes::Node {
+        match node {
+            nodes::Node::RuleChainNode{chain, pos_start, pos_end} => {
+                let mut flat: Vec<nodes::Node> = Vec::new();
+                for rule in chain {
+                    match self.simplify(rule) {
+                        nodes::Node::RuleChainNode{chain: inner, pos_start: _, pos_end: _} if !inner.is_empty() => {
+                            self.report.flattened += 1;
+                            flat.extend(inner);
+                        },
+                        rule => flat.push(rule)
+                    }
+                };
+                let mut merged: Vec<nodes::Node> = Vec::new();
+                for rule in flat {
+                    match (merged.last_mut(), rule) {
+                        (
+                            Some(nodes::Node::StrNode{string, caseless, pos_start: _, pos_end}),
+                            nodes::Node::StrNode{string: next, caseless: next_caseless, pos_start: _, pos_end: next_end}
+                        ) if *caseless == next_caseless => {
+                            self.report.merged += 1;
+                            string.push_str(&next);
+                            *pos_end = next_end;
+                        },
+                        (_, rule) => merged.push(rule)
+                    }
+                };
+                nodes::Node::RuleChainNode{chain: merged, pos_start, pos_end}
+            },
+            nodes::Node::BinOpNode{left: _, op: Pipe, right: _, pos_start: _, pos_end: _} => {
+                let mut alternatives = Vec::new();
+                into_alternatives(node, &mut alternatives);
+                let alternatives = alternatives.into_iter().map(|alternative| self.simplify(alternative)).collect();
+                self.factor(alternatives)
+            },
+            nodes::Node::PostFixNode{postfix, op, pos_start, pos_end} =>
+                nodes::Node::PostFixNode{postfix: Box::new(self.simplify(*postfix)), op, pos_start, pos_end},
+            nodes::Node::RuleCallNode{identifier, args, pos_start, pos_end} =>
+                nodes::Node::RuleCallNode{identifier, args: args.into_iter().map(|arg| self.simplify(arg)).collect(), pos_start, pos_end},
+            other => other
+        }
+    }
+
+    // Neighbouring alternatives that start the same way share that start,
+    // `'ab' | 'ac' | x` becomes `'a' ('b' | 'c') | x`. Only neighbours, since
+    // moving an alternative past another one could change which of them wins
+    fn factor(&mut self, alternatives: Vec<nodes::Node>) -> nodes::Node {
+        let mut groups: Vec<Vec<Vec<nodes::Node>>> = Vec::new();
+        for alternative in alternatives {
+            let elements = elements_of(alternative);
+            match groups.last_mut() {
+                Some(group) if compatible(&group[0], &elements) => group.push(elements),
+                _ => groups.push(vec![elements])
+            }
+        };
+        let mut factored = Vec::new();
+        for mut group in groups {
+            if group.len() == 1 {
+                factored.push(from_elements(group.pop().unwrap_or_default()));
+                continue
+            };
+            self.report.factored += 1;
+            let mut common = Vec::new();
+            while let Some(head) = group[0].first().map(|head| head.copy()) {
+                if group.iter().all(|elements| elements.first().is_some_and(|first| same(first, &head))) {
+                    for elements in group.iter_mut() {
+                        elements.remove(0);
+                    };
+                    common.push(head);
+                    continue
+                };
+                if let Some((prefix, caseless)) = literal_prefix(&group) {
+                    let (pos_start, pos_end) = head.get_pos();
+                    for elements in group.iter_mut() {
+                        let rest = match elements.remove(0) {
+                            nodes::Node::StrNode{string, caseless, pos_start, pos_end} => nodes::Node::StrNode{string: string[prefix.len()..].to_string(), caseless, pos_start, pos_end},
+                            other => other
+                        };
+                        if !matches!(&rest, nodes::Node::StrNode{string, caseless: _, pos_start: _, pos_end: _} if string.is_empty()) {
+                            elements.insert(0, rest);
+                        }
+                    };
+                    common.push(nodes::Node::StrNode{string: prefix, caseless, pos_start, pos_end});
+                };
+                break
+            };
+            if common.is_empty() {
+                factored.extend(group.into_iter().map(from_elements));
+                continue
+            };
+            let rest = self.factor(group.into_iter().map(from_elements).collect());
+            common.push(rest);
+            factored.push(from_elements(common));
+        };
+        let mut res = match factored.pop() {
+            Some(last) => last,
+            None => panic!("A choice without alternatives")
+        };
+        while let Some(previous) = factored.pop() {
+            let (pos_start, _) = previous.get_pos();
+            let (_, pos_end) = res.get_pos();
+            res = nodes::Node::BinOpNode{left: Box::new(previous), op: Pipe, right: Box::new(res), pos_start, pos_end};
+        };
+        return res
+    }
+}
+
+fn alternatives_of<'n>(node: &'n nodes::Node, alternatives: &mut Vec<&'n nodes::Node>) {
+    match node {
+        nodes::Node::BinOpNode{left, op: Pipe, right, pos_start: _, pos_end: _} => {
+            alternatives_of(left, alternatives);
+            alternatives_of(right, alternatives);
+        },
+        _ => alternatives.push(node)
+    }
+}
+
+fn into_alternatives(node: nodes::Node, alternatives: &mut Vec<nodes::Node>) {
+    match node {
+        nodes::Node::BinOpNode{left, op: Pipe, right, pos_start: _, pos_end: _} => {
+            into_alternatives(*left, alternatives);
+            into_alternatives(*right, alternatives);
+        },
+        other => alternatives.push(other)
+    }
+}
+
+fn elements_of(node: nodes::Node) -> Vec<nodes::Node> {
+    match node {
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => chain,
+        other => vec![other]
+    }
+}
+
+fn from_elements(mut elements: Vec<nodes::Node>) -> nodes::Node {
+    if elements.len() == 1 {
+        return elements.remove(0)
+    };
+    let (pos_start, pos_end) = match (elements.first(), elements.last()) {
+        (Some(first), Some(last)) => (first.get_pos().0, last.get_pos().1),
+        _ => return nodes::Node::RuleChainNode{chain: elements, pos_start: empty_position(), pos_end: empty_position()}
+    };
+    return nodes::Node::RuleChainNode{chain: elements, pos_start, pos_end}
+}
+
+fn empty_position() -> position::Position {
+    return position::Position{filename: String::new(), ftext: std::sync::Arc::from(""), index: 0u64, ln: 0u64, cn: 0u64}
+}
+
+// Whether two alternatives start with the same thing, or with literals that
+// start with the same character
+fn compatible(a: &[nodes::Node], b: &[nodes::Node]) -> bool {
+    match (a.first(), b.first()) {
+        (
+            Some(nodes::Node::StrNode{string: a, caseless: a_caseless, pos_start: _, pos_end: _}),
+            Some(nodes::Node::StrNode{string: b, caseless: b_caseless, pos_start: _, pos_end: _})
+        ) => a_caseless == b_caseless && !a.is_empty() && a.chars().next() == b.chars().next(),
+        (Some(a), Some(b)) => same(a, b),
+        _ => false
+    }
+}
+
+// The longest string every alternative's first literal starts with, when
+// they're all literals that ignore case or all ones that don't
+fn literal_prefix(group: &Vec<Vec<nodes::Node>>) -> Option<(String, bool)> {
+    let mut prefix: Option<(String, bool)> = None;
+    for elements in group {
+        let (string, caseless) = match elements.first() {
+            Some(nodes::Node::StrNode{string, caseless, pos_start: _, pos_end: _}) => (string, *caseless),
+            _ => return None
+        };
+        prefix = Some(match prefix {
+            None => (string.clone(), caseless),
+            Some((_, other)) if other != caseless => return None,
+            Some((prefix, _)) => (prefix.chars().zip(string.chars()).take_while(|(a, b)| a == b).map(|(a, _)| a).collect(), caseless)
+        });
+    };
+    return prefix.filter(|(prefix, _)| !prefix.is_empty())
+}
+
+fn same(a: &nodes::Node, b: &nodes::Node) -> bool {
+    match (a, b) {
+        (
+            nodes::Node::StrNode{string: a, caseless: a_caseless, pos_start: _, pos_end: _},
+            nodes::Node::StrNode{string: b, caseless: b_caseless, pos_start: _, pos_end: _}
+        ) => a == b && a_caseless == b_caseless,
+        (nodes::Node::RuleAccessNode{identifier: a, pos_start: _, pos_end: _}, nodes::Node::RuleAccessNode{identifier: b, pos_start: _, pos_end: _}) => a == b,
+        (
+            nodes::Node::RuleCallNode{identifier: a, args: a_args, pos_start: _, pos_end: _},
+            nodes::Node::RuleCallNode{identifier: b, args: b_args, pos_start: _, pos_end: _}
+        ) => a == b && a_args.len() == b_args.len() && a_args.iter().zip(b_args.iter()).all(|(a, b)| same(a, b)),
+        (nodes::Node::RuleChainNode{chain: a, pos_start: _, pos_end: _}, nodes::Node::RuleChainNode{chain: b, pos_start: _, pos_end: _}) =>
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same(a, b)),
+        (
+            nodes::Node::BinOpNode{left: a_left, op: a_op, right: a_right, pos_start: _, pos_end: _},
+            nodes::Node::BinOpNode{left: b_left, op: b_op, right: b_right, pos_start: _, pos_end: _}
+        ) => a_op == b_op && same(a_left, b_left) && same(a_right, b_right),
+        (
+            nodes::Node::PostFixNode{postfix: a, op: a_op, pos_start: _, pos_end: _},
+            nodes::Node::PostFixNode{postfix: b, op: b_op, pos_start: _, pos_end: _}
+        ) => a_op == b_op && same(a, b),
+        _ => false
+    }
+}
+
+fn size(node: &nodes::Node) -> usize {
+    match node {
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => 1 + chain.iter().map(size).sum::<usize>(),
+        nodes::Node::BinOpNode{left, op: _, right, pos_start: _, pos_end: _} => 1 + size(left) + size(right),
+        nodes::Node::PostFixNode{postfix, op: _, pos_start: _, pos_end: _} => 1 + size(postfix),
+        nodes::Node::RuleCallNode{identifier: _, args, pos_start: _, pos_end: _} => 1 + args.iter().map(size).sum::<usize>(),
+        _ => 1
+    }
+}
+
+fn uses(node: &nodes::Node, name: &String) -> bool {
+    match node {
+        nodes::Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => identifier == name,
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => chain.iter().any(|rule| uses(rule, name)),
+        nodes::Node::BinOpNode{left, op: _, right, pos_start: _, pos_end: _} => uses(left, name) || uses(right, name),
+        nodes::Node::PostFixNode{postfix, op: _, pos_start: _, pos_end: _} => uses(postfix, name),
+        nodes::Node::RuleCallNode{identifier, args, pos_start: _, pos_end: _} => identifier == name || args.iter().any(|arg| uses(arg, name)),
+        _ => false
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use crate::token;
 use crate::error;
 use crate::tokenTypes::TokenTypes::*;
@@ -16,8 +16,8 @@ impl Parser {
         token::Token{
             tok_type: Eof,
             tok_value: String::from(""),
-            pos_start: position::Position{filename: String::from("Yoi"), ftext: String::from(""), index: 0u64, ln: 1u64, cn: 1u64},
-            pos_end: position::Position{filename: String::from("Yoi"), ftext: String::from(""), index: 0u64, ln: 1u64, cn: 1u64}
+            pos_start: position::Position{filename: String::from("Yoi"), ftext: std::sync::Arc::from(""), index: 0u64, ln: 1u64, cn: 1u64},
+            pos_end: position::Position{filename: String::from("Yoi"), ftext: std::sync::Arc::from(""), index: 0u64, ln: 1u64, cn: 1u64}
         }
     }
 
@@ -53,7 +53,7 @@ impl Parser {
                         }
                     )
                 } else {
-                    return Ok(obj)
+                    return Ok(obj.chain_calls(&parameterised(&obj)))
                 }
             },
             Err(err) => Err(err)
@@ -113,8 +113,13 @@ impl Parser {
         let mut rules = Vec::new();
         let mut pos_end = self.eof_token().pos_end;
         let mut current_tok = self.current_tok(true);
-        while current_tok.tok_type == Identifier {
-            let rule = self.rule(Some(current_tok));
+        while current_tok.tok_type == Identifier || is_modifier(current_tok.tok_type) {
+            let mut modifiers = Vec::new();
+            while is_modifier(current_tok.tok_type) {
+                modifiers.push(current_tok.tok_type);
+                current_tok = self.current_tok(true);
+            };
+            let rule = self.rule(Some(current_tok), modifiers);
             rules.push(
                 match rule {
                     Ok(a) => {
@@ -131,12 +136,30 @@ impl Parser {
         return Ok(node);
     }
 
-    fn rule(&mut self, token: Option<token::Token>) -> Result<nodes::Node, error::Error> {
+    fn rule(&mut self, token: Option<token::Token>, modifiers: Vec<tokenTypes::TokenTypes>) -> Result<nodes::Node, error::Error> {
         let tok = match token {
             Some(t) => t,
             None => self.current_tok(true)
         };
-        let assign_tok = self.current_tok(true);
+        if tok.tok_type != Identifier {
+            return Err(
+                error::Error {
+                    name: String::from("ParseError"),
+                    message: format!("Expected rule name, got {}", tok.tok_type),
+                    pos_start: tok.pos_start.copy(),
+                    pos_end: tok.pos_end.copy()
+                }
+            );
+        };
+        let mut params = Vec::new();
+        let mut assign_tok = self.current_tok(true);
+        if assign_tok.tok_type == RParen && assign_tok.pos_start.index == tok.pos_end.index {
+            params = match self.params() {
+                Ok(a) => a,
+                Err(err) => return Err(err)
+            };
+            assign_tok = self.current_tok(true);
+        };
         if assign_tok.tok_type != Assign{
             return Err(
                 error::Error {
@@ -152,7 +175,62 @@ impl Parser {
             Err(err) => return Err(err)
         };
         let (_, pos_end) = lhs.get_pos();
-        return Ok(nodes::Node::RuleNode{rhs: tok.tok_value, lhs: Box::new(lhs), pos_start: assign_tok.pos_start, pos_end})
+        return Ok(nodes::Node::RuleNode{rhs: tok.tok_value, params, modifiers, lhs: Box::new(lhs), pos_start: assign_tok.pos_start, pos_end})
+    }
+
+    fn params(&mut self) -> Result<Vec<String>, error::Error> {
+        let mut params = Vec::new();
+        loop {
+            let param = self.current_tok(true);
+            if param.tok_type != Identifier {
+                return Err(
+                    error::Error {
+                        name: String::from("ParseError"),
+                        message: format!("Expected parameter name, got {}", param.tok_type),
+                        pos_start: param.pos_start.copy(),
+                        pos_end: param.pos_end.copy()
+                    }
+                )
+            };
+            params.push(param.tok_value);
+            let sep = self.current_tok(true);
+            match sep.tok_type {
+                Comma => (),
+                LParen => return Ok(params),
+                _ => return Err(
+                    error::Error {
+                        name: String::from("ParseError"),
+                        message: format!("Expected ',' or ')', got {}", sep.tok_type),
+                        pos_start: sep.pos_start.copy(),
+                        pos_end: sep.pos_end.copy()
+                    }
+                )
+            }
+        }
+    }
+
+    fn args(&mut self) -> Result<Vec<nodes::Node>, error::Error> {
+        let mut args = Vec::new();
+        loop {
+            let arg = match self.operation() {
+                Ok(a) => a,
+                Err(err) => return Err(err)
+            };
+            args.push(arg);
+            let sep = self.current_tok(true);
+            match sep.tok_type {
+                Comma => (),
+                LParen => return Ok(args),
+                _ => return Err(
+                    error::Error {
+                        name: String::from("ParseError"),
+                        message: format!("Expected ',' or ')', got {}", sep.tok_type),
+                        pos_start: sep.pos_start.copy(),
+                        pos_end: sep.pos_end.copy()
+                    }
+                )
+            }
+        }
     }
 
     fn operation(&mut self) -> Result<nodes::Node, error::Error> {
@@ -170,7 +248,7 @@ impl Parser {
             Err(err) => return Err(err)
         };
         ops.push(op);
-        while (!self.is_rhs()) && (!self.is_eof()) && (!self.is_tok(Pipe)) && (!self.is_tok(LParen)) {
+        while (!self.is_rhs()) && (!self.is_eof()) && (!self.is_tok(Pipe)) && (!self.is_tok(LParen)) && (!self.is_tok(Comma)) && (!self.is_modifier()) {
             let op = match self.atom(None) {
                 Ok(a) => a,
                 Err(err) => return Err(err)
@@ -187,6 +265,13 @@ impl Parser {
         return res;
     }
 
+    fn is_modifier(&mut self) -> bool {
+        let tok = self.current_tok(true);
+        let res = is_modifier(tok.tok_type);
+        self.add_tok(tok);
+        return res;
+    }
+
     fn is_eof(&mut self) -> bool {
         let eof = self.current_tok(true);
         let res = eof.tok_type == Eof;
@@ -198,9 +283,23 @@ impl Parser {
 
     fn is_rhs(&mut self) -> bool {
         let id = self.current_tok(true);
-        let assign = self.current_tok(true);
-        let res = id.tok_type == Identifier && assign.tok_type == Assign;
-        self.add_tok(assign);
+        let mut seen = Vec::new();
+        let mut next = self.current_tok(true);
+        if id.tok_type == Identifier && next.tok_type == RParen && next.pos_start.index == id.pos_end.index {
+            while [RParen, Identifier, Comma].contains(&next.tok_type) {
+                seen.push(next);
+                next = self.current_tok(true);
+            };
+            if next.tok_type == LParen {
+                seen.push(next);
+                next = self.current_tok(true);
+            };
+        };
+        let res = id.tok_type == Identifier && next.tok_type == Assign;
+        self.add_tok(next);
+        while let Some(tok) = seen.pop() {
+            self.add_tok(tok);
+        };
         self.add_tok(id);
         return res;
     }
@@ -252,11 +351,24 @@ impl Parser {
             None => self.current_tok(true)
         };
         let value = match tok {
-            _ if tok.tok_type == Str => {
-                let value = nodes::Node::StrNode{string: tok.tok_value, pos_start: tok.pos_start, pos_end: tok.pos_end};
+            _ if tok.tok_type == Str || tok.tok_type == IStr => {
+                let value = nodes::Node::StrNode{string: tok.tok_value, caseless: tok.tok_type == IStr, pos_start: tok.pos_start, pos_end: tok.pos_end};
                 Ok(value)
             },
             _ if tok.tok_type == Identifier => {
+                let next = self.current_tok(false);
+                if next.tok_type == RParen && next.pos_start.index == tok.pos_end.index {
+                    let args = match self.args() {
+                        Ok(a) => a,
+                        Err(err) => return Err(err)
+                    };
+                    let (_, pos_end) = match args.last() {
+                        Some(arg) => arg.get_pos(),
+                        None => (tok.pos_start.copy(), tok.pos_end.copy())
+                    };
+                    return Ok(nodes::Node::RuleCallNode{identifier: tok.tok_value, args, pos_start: tok.pos_start, pos_end})
+                };
+                self.add_tok(next);
                 let value = nodes::Node::RuleAccessNode{identifier: tok.tok_value, pos_start: tok.pos_start, pos_end: tok.pos_end};
                 Ok(value)
             },
@@ -293,3 +405,19 @@ impl Parser {
         return value
     }
 }
+
+// Definitions that take parameters, only a name from here followed right away by
+// `(` is a call, any other is followed by a parenthesised part of the chain
+fn parameterised(rules: &nodes::Node) -> HashSet<String> {
+    match rules {
+        nodes::Node::RulesNode{rules, pos_start: _, pos_end: _} => rules.iter().filter_map(|rule| match rule {
+            nodes::Node::RuleNode{rhs, params, modifiers: _, lhs: _, pos_start: _, pos_end: _} if !params.is_empty() => Some(rhs.clone()),
+            _ => None
+        }).collect(),
+        _ => HashSet::new()
+    }
+}
+
+fn is_modifier(tok_type: tokenTypes::TokenTypes) -> bool {
+    return [Caret, Tilde, At].contains(&tok_type)
+}
@@ -3,10 +3,12 @@ use crate::error;
 use crate::interpreter;
 use crate::tokenTypes;
 use crate::nodes;
-use crate::context;
+use crate::prelude;
+use crate::caseFolding;
 use crate::tokenTypes::TokenTypes::*;
 
-pub type Value = (String, String);
+// What the last thing matched, and where in the whole input what's left of it starts
+pub type Value = (String, usize);
 
 pub fn fst<A, B>(tuple: (A, B)) -> A {
     match tuple {
@@ -20,11 +22,11 @@ pub fn snd<A, B>(tuple: (A, B)) -> B {
     }
 }
 
-pub fn literal_parse(expected: String, string: Value, pos_start: position::Position, pos_end: position::Position) -> Result<Value, error::Error> {
-    let mut match_str = String::from("");
-    let mut i = 0;
-    let mut second = snd(string);
-    if expected.len() > second.len() {
+pub fn literal_parse(expected: String, caseless: bool, full: &str, string: Value, pos_start: position::Position, pos_end: position::Position) -> Result<Value, error::Error> {
+    let at = snd(string);
+    let second = &full[at..];
+    let length = expected.chars().count();
+    if length > second.chars().take(length).count() {
         return Err(
             error::Error{
                 name: String::from("InputError"),
@@ -34,13 +36,17 @@ pub fn literal_parse(expected: String, string: Value, pos_start: position::Posit
             }
         )
     };
-    while i<expected.len(){
-        let character = second.remove(0);
-        match_str = format!("{}{}", match_str, character);
-        i += 1;
+    let matched = literal_match(&expected, caseless, second);
+    let split = match matched {
+        Some(split) => split,
+        None => match second.char_indices().nth(length) {
+            Some((i, _)) => i,
+            None => second.len()
+        }
     };
-    if match_str == expected {
-        Ok((match_str, second))
+    let match_str = second[..split].to_string();
+    if matched.is_some() {
+        Ok((match_str, at + split))
     } else {
         Err(
             error::Error{
@@ -53,60 +59,101 @@ pub fn literal_parse(expected: String, string: Value, pos_start: position::Posit
     }
 }
 
+// Length in bytes of the part of `string` that `expected` matches, if it does
+pub fn literal_match(expected: &str, caseless: bool, string: &str) -> Option<usize> {
+    if !caseless {
+        return if string.starts_with(expected) { Some(expected.len()) } else { None }
+    };
+    let mut chars = string.char_indices();
+    for e in expected.chars() {
+        match chars.next() {
+            Some((_, c)) if caseFolding::fold(c) == caseFolding::fold(e) => (),
+            _ => return None
+        }
+    };
+    return match chars.next() {
+        Some((i, _)) => Some(i),
+        None => Some(string.len())
+    }
+}
+
+pub fn builtin_parse(builtin: prelude::Builtin, name: String, full: &str, string: Value, pos_start: position::Position, pos_end: position::Position) -> Result<Value, error::Error> {
+    let at = snd(string);
+    let second = &full[at..];
+    let current = second.chars().next();
+    let matched = builtin.matches(second).map(|length| second[..length].to_string());
+    match matched {
+        Some(match_str) => {
+            let left = at + match_str.len();
+            Ok((match_str, left))
+        },
+        None => Err(
+            error::Error{
+                name: String::from("InputError"),
+                message: match current {
+                    Some(c) => format!("expected {}, found {}", name, c),
+                    None => format!("expected {}, found end of input", name)
+                },
+                pos_start,
+                pos_end
+            }
+        )
+    }
+}
+
 pub fn copy_value(value: &Result<Value, error::Error>) -> Result<Value, error::Error> {
     match value {
         Ok(t) => {
             let (a, b) = t;
-            Ok((a.clone(), b.clone()))
+            Ok((a.clone(), *b))
         },
         Err(err) => Err(err.copy())
     }
 }
 
-pub fn handle_binary_op(
-        left: Box<nodes::Node>,
+pub fn handle_binary_op<'a>(
         op: tokenTypes::TokenTypes,
-        right: Box<nodes::Node>,
-        ctx: &mut context::Context,
-        input: Result<Value, error::Error>
-    ) -> Result<Value, error::Error> {
-    let left = interpreter::interpret(left, ctx, copy_value(&input));
+        right: &'a nodes::Node,
+        scope: interpreter::Env<'a>,
+        input: Value,
+        left: Result<Value, error::Error>
+    ) -> interpreter::Step<'a> {
     match op {
         Pipe => {
             match left {
-                Ok(a) => return Ok(a),
-                Err(_) => return interpreter::interpret(right, ctx, input)
+                Ok(a) => return interpreter::Step::Return(Ok(a)),
+                Err(_) => return interpreter::Step::Eval(right, scope, input)
             };
         },
         _ => panic!("This should not have happend, I expected {} but got {}", Pipe, op)
     }
 }
 
-pub fn handle_postfix_op(postfix: Box<nodes::Node>, op: tokenTypes::TokenTypes, context: &mut context::Context, input: Result<Value, error::Error>) -> Result<Value, error::Error> {
-    let post_node = (&postfix).copy();
-    let postfix_res = interpreter::interpret(Box::new(post_node), context, copy_value(&input));
+// Called every time the operand of a postfix operator finishes, for `*` and `+`
+// this either goes round again or hands back the last repetition that worked.
+// The operand comes with the scope it's matched in
+pub fn handle_postfix_op<'a>(
+        (postfix, scope): (&'a nodes::Node, interpreter::Env<'a>),
+        op: tokenTypes::TokenTypes,
+        input: Value,
+        repeated: bool,
+        postfix_res: Result<Value, error::Error>,
+        stack: &mut Vec<interpreter::Frame<'a>>
+    ) -> interpreter::Step<'a> {
     match op {
         QuestionMark => {
             match postfix_res {
-                Ok(a) => Ok(a),
-                Err(_) => return input
+                Ok(a) => interpreter::Step::Return(Ok(a)),
+                Err(_) => interpreter::Step::Return(Ok(input))
             }
         },
         Asterisk | Plus => {
-            let mut output = postfix_res;
-            match output {
-                Ok(_) => (),
-                Err(err) => return if op == Asterisk {input} else {Err(err)}
+            let output = match postfix_res {
+                Ok(a) => a,
+                Err(err) => return interpreter::Step::Return(if repeated || op == Asterisk {Ok(input)} else {Err(err)})
             };
-            loop {
-                let interpreted = interpreter::interpret(Box::new(postfix.copy()), context, copy_value(&output));
-                match interpreted {
-                    Ok(a) => {
-                        output = Ok(a);
-                    },
-                    Err(_) => return output
-                }
-            }
+            stack.push(interpreter::Frame::PostFix{postfix, op, input: output.clone(), repeated: true, scope: scope.clone()});
+            interpreter::Step::Eval(postfix, scope, output)
         },
         _ => panic!("Can't understand postfix operator, {}", op)
     }
@@ -1,7 +1,10 @@
+use std::sync::Arc;
+
+// Every position in the same text shares it, so copying one is cheap
 #[derive(Debug)]
 pub struct Position {
     pub filename: String,
-    pub ftext: String,
+    pub ftext: Arc<str>,
     pub index: u64,
     pub ln: u64,
     pub cn: u64
@@ -18,7 +21,16 @@ impl Position {
     return self
     }
 
+    // The position `offset` bytes into `ftext`
+    pub fn locate(filename: &str, ftext: &str, offset: usize) -> Position {
+        let mut position = Position{filename: filename.to_string(), ftext: Arc::from(ftext), index: 0u64, ln: 1u64, cn: 1u64};
+        for c in ftext[..offset].chars() {
+            position.advance(c);
+        };
+        return position
+    }
+
     pub fn copy(&self) -> Position {
-        return Position{index: self.index, ln: self.ln, cn: self.cn, filename: self.filename.clone(), ftext: self.ftext.clone()}
+        return Position{index: self.index, ln: self.ln, cn: self.cn, filename: self.filename.clone(), ftext: Arc::clone(&self.ftext)}
     }
 }
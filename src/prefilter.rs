@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use crate::nodes;
+use crate::context;
+use crate::prelude;
+use crate::tokenTypes::TokenTypes::*;
+
+// Literals every match of a rule has to have, `prefix` at its very start and
+// `required` somewhere inside it. Either can be empty when nothing is known
+pub struct Prefilter {
+    pub prefix: String,
+    pub required: String
+}
+
+impl Prefilter {
+    // Matches can't start after the last place `required` shows up, None when it
+    // doesn't show up at all
+    pub fn limit(&self, input: &str) -> Option<usize> {
+        if self.required.is_empty() {
+            return Some(input.len())
+        };
+        return input.rfind(&self.required)
+    }
+
+    // The first place at or after `from` a match could start
+    pub fn next(&self, input: &str, from: usize, limit: usize) -> Option<usize> {
+        if from > limit {
+            return None
+        };
+        if self.prefix.is_empty() {
+            return Some(from)
+        };
+        return input[from..].find(&self.prefix).map(|i| from + i).filter(|start| *start <= limit)
+    }
+}
+
+pub fn extract(context: &context::Context, rule: &str) -> Prefilter {
+    // Skipping a place is only the same as trying it when trying it couldn't have
+    // ended in anything other than a failed match
+    let lazy = match context.get(rule.to_string()) {
+        Some(lazy) if lazy.params.is_empty() && well_defined(context) => lazy,
+        _ => return Prefilter{prefix: String::new(), required: String::new()}
+    };
+    let mut extractor = Extractor{context, exact: HashMap::new(), prefix: HashMap::new(), required: HashMap::new(), visiting: vec![rule.to_string()]};
+    let prefix = extractor.prefix(&lazy.fun);
+    let required = extractor.required(&lazy.fun);
+    let required = if required.len() > prefix.len() { required } else { String::new() };
+    return Prefilter{prefix, required}
+}
+
+// The one string `node` can match, if there is only one
+pub fn exact(context: &context::Context, node: &nodes::Node) -> Option<String> {
+    let mut extractor = Extractor{context, exact: HashMap::new(), prefix: HashMap::new(), required: HashMap::new(), visiting: Vec::new()};
+    return extractor.exact(node)
+}
+
+// A literal every match of `node` starts with
+pub fn prefix(context: &context::Context, node: &nodes::Node) -> String {
+    let mut extractor = Extractor{context, exact: HashMap::new(), prefix: HashMap::new(), required: HashMap::new(), visiting: Vec::new()};
+    return extractor.prefix(node)
+}
+
+// Whether every rule used anywhere exists and gets the arguments it expects
+pub fn well_defined(context: &context::Context) -> bool {
+    return context.symbols.values().all(|lazy| uses_defined(&lazy.fun, &lazy.params, context))
+}
+
+fn uses_defined(node: &nodes::Node, params: &Vec<String>, context: &context::Context) -> bool {
+    match node {
+        nodes::Node::StrNode{string: _, caseless: _, pos_start: _, pos_end: _} => true,
+        nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => chain.iter().all(|rule| uses_defined(rule, params, context)),
+        nodes::Node::BinOpNode{left, op: _, right, pos_start: _, pos_end: _} => uses_defined(left, params, context) && uses_defined(right, params, context),
+        nodes::Node::PostFixNode{postfix, op: _, pos_start: _, pos_end: _} => uses_defined(postfix, params, context),
+        nodes::Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => params.contains(identifier) || match context.symbols.get(identifier) {
+            Some(lazy) => lazy.params.is_empty(),
+            None => prelude::lookup(identifier).is_some()
+        },
+        nodes::Node::RuleCallNode{identifier, args, pos_start: _, pos_end: _} => {
+            let arity = !params.contains(identifier) && match context.symbols.get(identifier) {
+                Some(lazy) => lazy.params.len() == args.len(),
+                None => false
+            };
+            arity && args.iter().all(|arg| uses_defined(arg, params, context))
+        },
+        _ => false
+    }
+}
+
+// Every answer here is allowed to know less than it could, which is also what a
+// rule that's still being looked at gives when it's reached again
+struct Extractor<'a> {
+    context: &'a context::Context,
+    exact: HashMap<String, Option<String>>,
+    prefix: HashMap<String, String>,
+    required: HashMap<String, String>,
+    visiting: Vec<String>
+}
+
+impl<'a> Extractor<'a> {
+    fn definition(&self, identifier: &String) -> Option<&'a nodes::Node> {
+        match self.context.symbols.get(identifier) {
+            Some(lazy) if lazy.params.is_empty() && !self.visiting.contains(identifier) => Some(&lazy.fun),
+            _ => None
+        }
+    }
+
+    // The one string the node can match, if there is only one
+    fn exact(&mut self, node: &nodes::Node) -> Option<String> {
+        match node {
+            nodes::Node::StrNode{string, caseless: false, pos_start: _, pos_end: _} => Some(string.clone()),
+            nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => {
+                let mut res = String::new();
+                for rule in chain {
+                    res.push_str(&self.exact(rule)?);
+                };
+                Some(res)
+            },
+            nodes::Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => {
+                if let Some(known) = self.exact.get(identifier) {
+                    return known.clone()
+                };
+                let body = self.definition(identifier)?;
+                self.visiting.push(identifier.clone());
+                let res = self.exact(body);
+                self.visiting.pop();
+                self.exact.insert(identifier.clone(), res.clone());
+                res
+            },
+            _ => None
+        }
+    }
+
+    fn prefix(&mut self, node: &nodes::Node) -> String {
+        match node {
+            nodes::Node::StrNode{string, caseless: false, pos_start: _, pos_end: _} => string.clone(),
+            nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => {
+                let mut res = String::new();
+                for rule in chain {
+                    match self.exact(rule) {
+                        Some(string) => res.push_str(&string),
+                        None => {
+                            res.push_str(&self.prefix(rule));
+                            break
+                        }
+                    }
+                };
+                res
+            },
+            nodes::Node::BinOpNode{left, op: Pipe, right, pos_start: _, pos_end: _} => {
+                let (left, right) = (self.prefix(left), self.prefix(right));
+                common_prefix(&left, &right)
+            },
+            nodes::Node::PostFixNode{postfix, op: Plus, pos_start: _, pos_end: _} => self.prefix(postfix),
+            nodes::Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => {
+                if let Some(known) = self.prefix.get(identifier) {
+                    return known.clone()
+                };
+                let body = match self.definition(identifier) {
+                    Some(body) => body,
+                    None => return String::new()
+                };
+                self.visiting.push(identifier.clone());
+                let res = self.prefix(body);
+                self.visiting.pop();
+                self.prefix.insert(identifier.clone(), res.clone());
+                res
+            },
+            _ => String::new()
+        }
+    }
+
+    // The longest literal found in every match
+    fn required(&mut self, node: &nodes::Node) -> String {
+        match node {
+            nodes::Node::StrNode{string, caseless: false, pos_start: _, pos_end: _} => string.clone(),
+            nodes::Node::RuleChainNode{chain, pos_start: _, pos_end: _} => {
+                let mut best = String::new();
+                let mut run = String::new();
+                for rule in chain {
+                    match self.exact(rule) {
+                        Some(string) => run.push_str(&string),
+                        None => {
+                            run.push_str(&self.prefix(rule));
+                            best = longest(best, run);
+                            best = longest(best, self.required(rule));
+                            run = String::new();
+                        }
+                    }
+                };
+                longest(best, run)
+            },
+            nodes::Node::BinOpNode{left, op: Pipe, right, pos_start: _, pos_end: _} => {
+                let (left, right) = (self.required(left), self.required(right));
+                if left.contains(&right) { right } else if right.contains(&left) { left } else { String::new() }
+            },
+            nodes::Node::PostFixNode{postfix, op: Plus, pos_start: _, pos_end: _} => self.required(postfix),
+            nodes::Node::RuleAccessNode{identifier, pos_start: _, pos_end: _} => {
+                if let Some(known) = self.required.get(identifier) {
+                    return known.clone()
+                };
+                let body = match self.definition(identifier) {
+                    Some(body) => body,
+                    None => return String::new()
+                };
+                self.visiting.push(identifier.clone());
+                let res = self.required(body);
+                self.visiting.pop();
+                self.required.insert(identifier.clone(), res.clone());
+                res
+            },
+            _ => String::new()
+        }
+    }
+}
+
+fn common_prefix(a: &str, b: &str) -> String {
+    return a.chars().zip(b.chars()).take_while(|(x, y)| x == y).map(|(x, _)| x).collect()
+}
+
+fn longest(a: String, b: String) -> String {
+    return if b.len() > a.len() { b } else { a }
+}
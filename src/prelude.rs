@@ -0,0 +1,54 @@
+#[derive(Copy, Clone)]
+pub enum Builtin {
+    Class(fn(char) -> bool),
+    Newline,
+    EndOfInput
+}
+
+impl Builtin {
+    pub fn matches(&self, string: &str) -> Option<usize> {
+        match self {
+            Builtin::Class(predicate) => match string.chars().next() {
+                Some(c) if predicate(c) => Some(c.len_utf8()),
+                _ => None
+            },
+            Builtin::Newline => ["\r\n", "\n", "\r"].iter().find(|nl| string.starts_with(*nl)).map(|nl| nl.len()),
+            Builtin::EndOfInput => if string.is_empty() { Some(0) } else { None }
+        }
+    }
+}
+
+pub fn lookup(name: &str) -> Option<Builtin> {
+    match name {
+        "DIGIT" => Some(Builtin::Class(|c| c.is_ascii_digit())),
+        "ALPHA" => Some(Builtin::Class(|c| c.is_ascii_alphabetic())),
+        "ALNUM" => Some(Builtin::Class(|c| c.is_ascii_alphanumeric())),
+        "HEXDIG" => Some(Builtin::Class(|c| c.is_ascii_hexdigit())),
+        "WS" => Some(Builtin::Class(|c| c == ' ' || c == '\t' || c == '\r' || c == '\n')),
+        "NEWLINE" => Some(Builtin::Newline),
+        "ANY" => Some(Builtin::Class(|_| true)),
+        "EOI" => Some(Builtin::EndOfInput),
+        "LETTER" => Some(Builtin::Class(|c| c.is_alphabetic())),
+        "UPPERCASE" => Some(Builtin::Class(|c| c.is_uppercase())),
+        "LOWERCASE" => Some(Builtin::Class(|c| c.is_lowercase())),
+        "NUMBER" => Some(Builtin::Class(|c| c.is_numeric())),
+        "SPACE" => Some(Builtin::Class(|c| c.is_whitespace())),
+        "CONTROL" => Some(Builtin::Class(|c| c.is_control())),
+        _ => None
+    }
+}
+
+// The characters a class matches as sorted ranges, only for the classes small
+// enough to spell out, the Unicode ones are left to their predicates
+pub fn ranges(name: &str) -> Option<Vec<(u32, u32)>> {
+    let ranges: Vec<(char, char)> = match name {
+        "DIGIT" => vec![('0', '9')],
+        "ALPHA" => vec![('A', 'Z'), ('a', 'z')],
+        "ALNUM" => vec![('0', '9'), ('A', 'Z'), ('a', 'z')],
+        "HEXDIG" => vec![('0', '9'), ('A', 'F'), ('a', 'f')],
+        "WS" => vec![('\t', '\n'), ('\r', '\r'), (' ', ' ')],
+        "ANY" => vec![('\0', char::MAX)],
+        _ => return None
+    };
+    return Some(ranges.iter().map(|(lo, hi)| (*lo as u32, *hi as u32)).collect())
+}
@@ -2,30 +2,305 @@ use crate::position;
 use crate::lexer;
 use crate::parser;
 use crate::interpreter;
+use crate::nodes;
+use crate::error;
+use crate::compiler;
+use crate::vm;
+use crate::context;
+use crate::prefilter;
+use crate::optimiser;
+use crate::dfa;
+use crate::matcher;
+use crate::executor;
+use crate::incremental;
+use crate::tree;
+use crate::actions;
+use crate::linter;
+use crate::analysis;
 
-pub fn string_left(regex:String, input: String) -> String {
-    let position = position::Position{filename: String::from("Yoi"), ftext: regex, index: 0u64, ln: 1u64, cn: 1u64};
-    let mut lexer = lexer::Lexer{current_index: 0usize, chars: position.ftext.as_bytes().to_vec(), position: position};
-    let mut toks = match lexer.lex() {
-        Ok(a) => a,
-        Err(e) => panic!(format!("{}", e))
-    };
-    toks.reverse();
-    let mut parser = parser::Parser{tokens: toks};
-    let ast = match parser.parse() {
-        Ok(n) => n,
-        Err(e) => {
-            panic!("{}", e)
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Engine {
+    Interpreter,
+    Vm
+}
+
+pub struct Grammar {
+    pub ast: nodes::Node,
+    pub context: context::Context,
+    program: Option<compiler::Program>,
+    // Goes up every time the definitions change, so `reparse` knows not to
+    // reuse matches from a document made before that
+    generation: u64
+}
+
+// Matching only ever reads a grammar, so one can be shared by every thread
+// matching with it. This stops compiling if that ever changes
+const _: fn() = || {
+    fn shared<T: Send + Sync>() {}
+    shared::<Grammar>();
+};
+
+impl Grammar {
+    pub fn new(regex: String) -> Result<Grammar, error::Error> {
+        let position = position::Position{filename: String::from("Yoi"), ftext: std::sync::Arc::from(regex), index: 0u64, ln: 1u64, cn: 1u64};
+        let mut lexer = lexer::Lexer{current_index: 0usize, chars: position.ftext.chars().collect(), position: position, comments: Vec::new()};
+        let mut toks = match lexer.lex() {
+            Ok(a) => a,
+            Err(err) => return Err(err)
+        };
+        toks.reverse();
+        let mut parser = parser::Parser{tokens: toks};
+        let ast = match parser.parse() {
+            Ok(n) => n,
+            Err(err) => return Err(err)
+        };
+        let context = match interpreter::prepare(&ast) {
+            Ok(a) => a,
+            Err(err) => return Err(err)
+        };
+        return Ok(Grammar{ast, context, program: None, generation: 0})
+    }
+
+    pub fn engine(&self) -> Engine {
+        return if self.program.is_some() { Engine::Vm } else { Engine::Interpreter }
+    }
+
+    pub fn set_engine(&mut self, engine: Engine) -> Result<(), error::Error> {
+        self.program = match engine {
+            Engine::Interpreter => None,
+            Engine::Vm => match compiler::compile_context(&self.context) {
+                Ok(program) => Some(program),
+                Err(err) => return Err(err)
+            }
+        };
+        self.generation += 1;
+        return Ok(())
+    }
+
+    // Rewrites the definitions into quicker ones that match the same inputs,
+    // `optimiser::dump(&grammar.context)` shows what they turned into
+    pub fn optimise(&mut self) -> Result<optimiser::Report, error::Error> {
+        let report = optimiser::optimise(&mut self.context);
+        self.context.dfas = dfa::compile_regular(&self.context);
+        self.generation += 1;
+        if self.program.is_some() {
+            if let Err(err) = self.set_engine(Engine::Vm) {
+                return Err(err)
+            }
+        };
+        return Ok(report)
+    }
+
+    // Things in the grammar as it was written that are likely mistakes, see `linter`
+    pub fn lint(&self) -> Vec<linter::Warning> {
+        return linter::lint(&self.ast)
+    }
+
+    // Nullable, FIRST and FOLLOW of every definition, see `analysis::GrammarAnalysis`
+    pub fn analysis(&self) -> analysis::GrammarAnalysis {
+        return self.analysis_rule("main")
+    }
+
+    // The same with FOLLOW worked out for matches of `rule` instead of `main`
+    pub fn analysis_rule(&self, rule: &str) -> analysis::GrammarAnalysis {
+        return analysis::GrammarAnalysis::new(&self.context, rule)
+    }
+
+    // Whether every definition could be a plain regex or needs more than that
+    pub fn classify(&self) -> analysis::Classification {
+        return analysis::classify(&self.context)
+    }
+
+    pub fn options(&self) -> context::MatchOptions {
+        return self.context.options.clone()
+    }
+
+    // Limits every match made with this grammar from now on, on either engine
+    pub fn set_options(&mut self, options: context::MatchOptions) {
+        if let Some(program) = &mut self.program {
+            program.options = options.clone();
+        };
+        self.context.options = options;
+    }
+
+    pub fn string_left_rule(&self, rule: &str, input: String) -> Result<String, error::Error> {
+        match self.match_at(rule, &input, 0) {
+            Ok(end) => Ok(input[end..].to_string()),
+            Err(err) => Err(err)
         }
-    };
-    let input = (String::from(""), input);
-    let (_, left) = match interpreter::run_interpreter(ast, input) {
+    }
+
+    // Matches the rule against the input from byte `start` on, without copying any
+    // of it, and returns the byte the match ends at
+    pub fn match_at(&self, rule: &str, input: &str, start: usize) -> Result<usize, error::Error> {
+        if let Some(program) = &self.program {
+            let entry = match program.entry(rule) {
+                Ok(a) => a,
+                Err(err) => return Err(err)
+            };
+            return vm::run(program, entry, input, start)
+        };
+        return interpreter::run_context_rule(&self.context, rule, input, start)
+    }
+
+    pub fn is_match(&self, input: String) -> Result<bool, error::Error> {
+        return self.is_match_rule("main", input)
+    }
+
+    pub fn is_match_rule(&self, rule: &str, input: String) -> Result<bool, error::Error> {
+        match self.match_at(rule, &input, 0) {
+            Ok(end) => Ok(end == input.len()),
+            Err(err) => if err.name == "InputError" { Ok(false) } else { Err(err) }
+        }
+    }
+
+    pub fn matcher(&self) -> Result<matcher::Matcher, error::Error> {
+        return self.matcher_rule("main")
+    }
+
+    // Matches the rule against input fed to it a chunk at a time, always on the
+    // bytecode engine whichever one the grammar uses
+    pub fn matcher_rule(&self, rule: &str) -> Result<matcher::Matcher, error::Error> {
+        let program = match compiler::compile_context(&self.context) {
+            Ok(a) => a,
+            Err(err) => return Err(err)
+        };
+        return match program.entry(rule) {
+            Ok(entry) => Ok(matcher::Matcher::new(program, entry)),
+            Err(err) => Err(err)
+        }
+    }
+
+    pub fn is_match_all<I>(&self, inputs: I) -> Vec<Result<bool, error::Error>> where I: IntoIterator<Item = String>, I::IntoIter: Send {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        return self.is_match_all_rule("main", inputs, threads)
+    }
+
+    // Matches every input on its own, spread over `threads` new threads that share
+    // this grammar, and gives back the results in the order the inputs came in
+    pub fn is_match_all_rule<I>(&self, rule: &str, inputs: I, threads: usize) -> Vec<Result<bool, error::Error>> where I: IntoIterator<Item = String>, I::IntoIter: Send {
+        return self.is_match_all_with(rule, inputs, &executor::Threads(threads))
+    }
+
+    // The same on the threads of `executor`, which can keep them around between calls
+    pub fn is_match_all_with<I, E>(&self, rule: &str, inputs: I, executor: &E) -> Vec<Result<bool, error::Error>> where I: IntoIterator<Item = String>, I::IntoIter: Send, E: executor::Executor + ?Sized {
+        let queue = std::sync::Mutex::new(inputs.into_iter().enumerate());
+        let finished: std::sync::Mutex<Vec<(usize, Result<bool, error::Error>)>> = std::sync::Mutex::new(Vec::new());
+        let work = || {
+            let mut done = Vec::new();
+            loop {
+                let next = match queue.lock() {
+                    Ok(mut inputs) => inputs.next(),
+                    Err(_) => None
+                };
+                match next {
+                    Some((i, input)) => done.push((i, self.is_match_rule(rule, input))),
+                    None => break
+                }
+            };
+            if let Ok(mut finished) = finished.lock() {
+                finished.extend(done);
+            }
+        };
+        executor.execute((0..executor.workers().max(1)).map(|_| Box::new(work) as executor::Job).collect());
+        let mut results = match finished.into_inner() {
             Ok(a) => a,
-            Err(err) => panic!("{}", err)
+            Err(poisoned) => poisoned.into_inner()
         };
-    return left
+        results.sort_by_key(|(i, _)| *i);
+        return results.into_iter().map(|(_, res)| res).collect()
+    }
+
+    pub fn document(&self, input: String) -> incremental::Document {
+        return self.document_rule("main", input)
+    }
+
+    // Matches on the interpreter, whichever engine the grammar uses, since that's
+    // the one that remembers what it found so `reparse` can reuse it
+    pub fn document_rule(&self, rule: &str, input: String) -> incremental::Document {
+        return incremental::Document::new(&self.context, self.generation, rule, input)
+    }
+
+    // The document with the bytes in `range` replaced, matched again reusing
+    // whatever the edit didn't change. Nothing is reused if the grammar was
+    // optimised or switched engines since the document was made
+    pub fn reparse(&self, mut previous: incremental::Document, range: std::ops::Range<usize>, replacement: &str) -> incremental::Document {
+        previous.edit(&self.context, self.generation, range, replacement);
+        return previous
+    }
+
+    pub fn parse(&self, input: String) -> Result<tree::Tree, error::Error> {
+        return self.parse_rule("main", input)
+    }
+
+    // The tree of definitions that matched, always found with the interpreter
+    pub fn parse_rule(&self, rule: &str, input: String) -> Result<tree::Tree, error::Error> {
+        return interpreter::parse_context_rule(&self.context, rule, input)
+    }
+
+    pub fn evaluate<T>(&self, actions: &actions::Actions<T>, input: String) -> Result<Vec<T>, error::Error> {
+        return self.evaluate_rule("main", actions, input)
+    }
+
+    // What the actions turn the whole input into, it's an error for the rule to
+    // match only part of it
+    pub fn evaluate_rule<T>(&self, rule: &str, actions: &actions::Actions<T>, input: String) -> Result<Vec<T>, error::Error> {
+        let tree = match self.parse_rule(rule, input.clone()) {
+            Ok(a) => a,
+            Err(err) => return Err(err)
+        };
+        if tree.end < input.len() {
+            let (pos_start, pos_end) = self.context.pos();
+            return Err(
+                error::Error{
+                    name: String::from("InputError"),
+                    message: format!("expected end of input, found {}", &input[tree.end..]),
+                    pos_start,
+                    pos_end
+                }
+            )
+        };
+        return Ok(actions.evaluate(&tree, &input))
+    }
+
+    pub fn search(&self, input: String) -> Result<Option<(usize, usize)>, error::Error> {
+        return self.search_rule("main", input)
+    }
+
+    // Returns the byte span of the first place in the input where the rule matches.
+    // Places that can't have the literals every match needs are skipped without trying
+    pub fn search_rule(&self, rule: &str, input: String) -> Result<Option<(usize, usize)>, error::Error> {
+        let prefilter = prefilter::extract(&self.context, rule);
+        let limit = match prefilter.limit(&input) {
+            Some(a) => a,
+            None => return Ok(None)
+        };
+        let mut from = 0usize;
+        while let Some(start) = prefilter.next(&input, from, limit) {
+            match self.match_at(rule, &input, start) {
+                Ok(end) => return Ok(Some((start, end))),
+                Err(err) => if err.name != "InputError" { return Err(err) }
+            };
+            from = match input[start..].chars().next() {
+                Some(c) => start + c.len_utf8(),
+                None => break
+            };
+        };
+        return Ok(None)
+    }
+}
+
+pub fn string_left(regex:String, input: String) -> String {
+    let grammar = match Grammar::new(regex) {
+        Ok(a) => a,
+        Err(e) => panic!("{}", e)
+    };
+    match grammar.string_left_rule("main", input) {
+        Ok(a) => a,
+        Err(err) => panic!("{}", err)
+    }
 }
 
 pub fn match_string(regex:String, input: String) -> bool {
-    return string_left(regex, input) == ""
-}
\ No newline at end of file
+    return string_left(regex, input).is_empty()
+}
@@ -3,6 +3,7 @@
 #[derive(Copy, Clone)]
 pub enum TokenTypes {
     Str,
+    IStr,
     Comma,
     LParen,
     RParen,
@@ -13,6 +14,10 @@ pub enum TokenTypes {
     QuestionMark,
     Pipe,
     Plus,
+    Caret,
+    Tilde,
+    At,
+    Comment,
     Eof
 }
 
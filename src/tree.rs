@@ -0,0 +1,278 @@
+use std::collections::{BTreeMap, HashMap};
+
+// A definition that matched, the bytes of the input it matched, and the
+// definitions that matched inside it in the order they did
+#[derive(Clone, PartialEq, Debug)]
+pub struct Tree {
+    pub rule: String,
+    pub start: usize,
+    pub end: usize,
+    pub children: Vec<Tree>
+}
+
+// Definitions whose names start with an underscore leave no tree of their own,
+// whatever matched inside them goes straight to their parent
+pub fn silent(rule: &str) -> bool {
+    return rule.starts_with('_')
+}
+
+// Input that nests deeply makes trees just as deep, so they're taken apart
+// without recursing
+impl Drop for Tree {
+    fn drop(&mut self) {
+        let mut pending = std::mem::take(&mut self.children);
+        while let Some(mut tree) = pending.pop() {
+            pending.append(&mut tree.children);
+        }
+    }
+}
+
+impl Tree {
+    // What it matched out of the input it was matched against
+    pub fn text<'a>(&self, input: &'a str) -> &'a str {
+        return &input[self.start..self.end]
+    }
+
+    // Every tree matched by `rule` in here, this one included, in the order they start
+    pub fn find_all(&self, rule: &str) -> Vec<&Tree> {
+        let mut found = Vec::new();
+        let mut pending = vec![self];
+        while let Some(tree) = pending.pop() {
+            if tree.rule == rule {
+                found.push(tree);
+            };
+            pending.extend(tree.children.iter().rev());
+        };
+        return found
+    }
+
+    // Calls `enter` on the way down to each tree and `exit` on the way back up
+    pub fn walk<V: Visitor>(&self, visitor: &mut V) {
+        let mut pending: Vec<(&Tree, bool)> = vec![(self, false)];
+        while let Some((tree, visited)) = pending.pop() {
+            if visited {
+                visitor.exit(tree);
+                continue
+            };
+            visitor.enter(tree);
+            pending.push((tree, true));
+            pending.extend(tree.children.iter().rev().map(|child| (child, false)));
+        }
+    }
+
+    // On one line, `(rule start end child ...)` with what the trees without any
+    // children matched in quotes after their span
+    pub fn to_sexp(&self, input: &str) -> String {
+        let mut out = String::new();
+        let mut pending: Vec<Option<&Tree>> = vec![Some(self)];
+        while let Some(next) = pending.pop() {
+            let tree = match next {
+                Some(a) => a,
+                None => {
+                    out.push(')');
+                    continue
+                }
+            };
+            if !out.is_empty() && !out.ends_with('(') {
+                out.push(' ');
+            };
+            out.push_str(&format!("({} {} {}", tree.rule, tree.start, tree.end));
+            if tree.children.is_empty() {
+                out.push_str(&format!(" {})", quote(tree.text(input))));
+                continue
+            };
+            pending.push(None);
+            pending.extend(tree.children.iter().rev().map(Some));
+        };
+        return out
+    }
+
+    // Indented by two spaces with the keys always in the same order, so it can be
+    // compared against a file as it is
+    pub fn to_json(&self, input: &str) -> String {
+        let mut out = String::new();
+        // A tree to write out, or the end of one whose children have been, along
+        // with how far in it is and whether it's the last of its siblings
+        let mut pending: Vec<(Option<&Tree>, usize, bool)> = vec![(Some(self), 0, true)];
+        while let Some((next, depth, last)) = pending.pop() {
+            let (pad, inner) = ("  ".repeat(depth), "  ".repeat(depth + 1));
+            let comma = if last { "" } else { "," };
+            let tree = match next {
+                Some(a) => a,
+                None => {
+                    out.push_str(&format!("{}]\n{}}}{}\n", inner, pad, comma));
+                    continue
+                }
+            };
+            out.push_str(&format!("{}{{\n", pad));
+            out.push_str(&format!("{}\"rule\": {},\n", inner, quote(&tree.rule)));
+            out.push_str(&format!("{}\"start\": {},\n", inner, tree.start));
+            out.push_str(&format!("{}\"end\": {},\n", inner, tree.end));
+            out.push_str(&format!("{}\"text\": {},\n", inner, quote(tree.text(input))));
+            if tree.children.is_empty() {
+                out.push_str(&format!("{}\"children\": []\n{}}}{}\n", inner, pad, comma));
+                continue
+            };
+            out.push_str(&format!("{}\"children\": [\n", inner));
+            pending.push((None, depth, last));
+            let count = tree.children.len();
+            pending.extend(tree.children.iter().enumerate().rev().map(|(i, child)| (Some(child), depth + 2, i + 1 == count)));
+        };
+        out.truncate(out.trim_end().len());
+        return out
+    }
+
+    // Everything in here by the definition that matched it, each in the order they start
+    pub fn captures(&self) -> BTreeMap<&str, Vec<&Tree>> {
+        let mut captures: BTreeMap<&str, Vec<&Tree>> = BTreeMap::new();
+        let mut pending = vec![self];
+        while let Some(tree) = pending.pop() {
+            captures.entry(&tree.rule).or_default().push(tree);
+            pending.extend(tree.children.iter().rev());
+        };
+        return captures
+    }
+
+    // `captures` as one JSON object on a line of its own for each definition, the
+    // definitions sorted by name
+    pub fn captures_json(&self, input: &str) -> String {
+        let lines: Vec<String> = self.captures().iter().map(|(rule, trees)| {
+            let spans: Vec<String> = trees.iter().map(|tree| format!("{{\"start\": {}, \"end\": {}, \"text\": {}}}", tree.start, tree.end, quote(tree.text(input)))).collect();
+            format!("  {}: [{}]", quote(rule), spans.join(", "))
+        }).collect();
+        if lines.is_empty() {
+            return String::from("{}")
+        };
+        return format!("{{\n{}\n}}", lines.join(",\n"))
+    }
+
+    pub fn cursor(&self) -> Cursor<'_> {
+        return Cursor{node: self, path: Vec::new()}
+    }
+}
+
+// Quoted and escaped the way JSON wants, which S-expressions are happy with too
+fn quote(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c)
+        }
+    };
+    out.push('"');
+    return out
+}
+
+// Both do nothing unless they're given something to do, look at `tree.rule` to
+// tell which definition it is
+pub trait Visitor {
+    fn enter(&mut self, _tree: &Tree) {}
+    fn exit(&mut self, _tree: &Tree) {}
+}
+
+// Called with a tree matched by the definition it was given for
+pub type Handler<'h> = Box<dyn FnMut(&Tree) + 'h>;
+
+// A `Visitor` made of handlers for the definitions that have one, every other
+// tree is walked past without calling anything
+pub struct Visitors<'h> {
+    enter: HashMap<String, Handler<'h>>,
+    exit: HashMap<String, Handler<'h>>
+}
+
+impl<'h> Default for Visitors<'h> {
+    fn default() -> Visitors<'h> {
+        return Visitors::new()
+    }
+}
+
+impl<'h> Visitors<'h> {
+    pub fn new() -> Visitors<'h> {
+        return Visitors{enter: HashMap::new(), exit: HashMap::new()}
+    }
+
+    // Replaces whatever the rule had before
+    pub fn on_enter<F>(&mut self, rule: &str, handler: F) -> &mut Visitors<'h> where F: FnMut(&Tree) + 'h {
+        self.enter.insert(rule.to_string(), Box::new(handler));
+        return self
+    }
+
+    pub fn on_exit<F>(&mut self, rule: &str, handler: F) -> &mut Visitors<'h> where F: FnMut(&Tree) + 'h {
+        self.exit.insert(rule.to_string(), Box::new(handler));
+        return self
+    }
+}
+
+impl Visitor for Visitors<'_> {
+    fn enter(&mut self, tree: &Tree) {
+        if let Some(handler) = self.enter.get_mut(&tree.rule) {
+            handler(tree);
+        }
+    }
+
+    fn exit(&mut self, tree: &Tree) {
+        if let Some(handler) = self.exit.get_mut(&tree.rule) {
+            handler(tree);
+        }
+    }
+}
+
+// Moves around a tree one step at a time. Every move says whether it could be
+// made, and stays put when it couldn't
+pub struct Cursor<'a> {
+    node: &'a Tree,
+    // The parents of `node` from the root down, and which child the next one is
+    path: Vec<(&'a Tree, usize)>
+}
+
+impl<'a> Cursor<'a> {
+    pub fn node(&self) -> &'a Tree {
+        return self.node
+    }
+
+    pub fn depth(&self) -> usize {
+        return self.path.len()
+    }
+
+    pub fn parent(&mut self) -> bool {
+        match self.path.pop() {
+            Some((parent, _)) => {
+                self.node = parent;
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn first_child(&mut self) -> bool {
+        match self.node.children.first() {
+            Some(child) => {
+                self.path.push((self.node, 0));
+                self.node = child;
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn next_sibling(&mut self) -> bool {
+        let (parent, index) = match self.path.last_mut() {
+            Some(a) => a,
+            None => return false
+        };
+        match parent.children.get(*index + 1) {
+            Some(sibling) => {
+                *index += 1;
+                self.node = sibling;
+                true
+            },
+            None => false
+        }
+    }
+}
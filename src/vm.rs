@@ -0,0 +1,270 @@
+use crate::compiler;
+use crate::error;
+use crate::position;
+use crate::interpreter;
+use crate::prelude;
+use crate::parserCombinators;
+use crate::context;
+
+pub enum Instruction {
+    Char(char, usize),
+    Literal(String, bool, usize),
+    Builtin(prelude::Builtin, String, usize),
+    Dispatch(usize),
+    Choice(usize),
+    Commit(usize),
+    Call(usize, usize),
+    Return,
+    Fail(usize),
+    End
+}
+
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Instruction::Char(c, _) => write!(f, "char {:?}", c),
+            Instruction::Literal(string, caseless, _) => write!(f, "literal {}{:?}", if *caseless { "i" } else { "" }, string),
+            Instruction::Builtin(_, name, _) => write!(f, "builtin {}", name),
+            Instruction::Dispatch(table) => write!(f, "dispatch #{}", table),
+            Instruction::Choice(target) => write!(f, "choice {}", target),
+            Instruction::Commit(target) => write!(f, "commit {}", target),
+            Instruction::Call(target, _) => write!(f, "call {}", target),
+            Instruction::Return => write!(f, "return"),
+            Instruction::Fail(_) => write!(f, "fail"),
+            Instruction::End => write!(f, "end")
+        }
+    }
+}
+
+// Where to jump depending on the next character of the input. Non ASCII characters
+// are looked up in sorted ranges, falling back to `other`
+pub struct Dispatch {
+    pub ascii: [usize; 128],
+    pub ranges: Vec<(u32, u32, usize)>,
+    pub other: usize,
+    pub end: usize
+}
+
+impl Dispatch {
+    pub fn target(&self, next: Option<char>) -> usize {
+        let c = match next {
+            Some(c) => c as u32,
+            None => return self.end
+        };
+        if c < 128 {
+            return self.ascii[c as usize]
+        };
+        match self.ranges.binary_search_by(|(lo, hi, _)| if *hi < c { std::cmp::Ordering::Less } else if *lo > c { std::cmp::Ordering::Greater } else { std::cmp::Ordering::Equal }) {
+            Ok(i) => self.ranges[i].2,
+            Err(_) => self.other
+        }
+    }
+
+    pub fn retarget(&mut self, addresses: &[usize]) {
+        for target in self.ascii.iter_mut() {
+            *target = addresses[*target];
+        };
+        for (_, _, target) in self.ranges.iter_mut() {
+            *target = addresses[*target];
+        };
+        self.other = addresses[self.other];
+        self.end = addresses[self.end];
+    }
+}
+
+// How many steps go by between looking at the clock and for cancellation
+const POLL_INTERVAL: u64 = 1024;
+
+struct Backtrack {
+    pc: usize,
+    pos: usize,
+    calls: usize
+}
+
+pub enum Outcome {
+    Done(Result<usize, error::Error>),
+    NeedMoreInput
+}
+
+// Where a run of the program is, kept between calls so a match can stop at the
+// end of the input it has been given and carry on once there's more of it
+pub struct Machine {
+    pc: usize,
+    pos: usize,
+    calls: Vec<usize>,
+    backtrack: Vec<Backtrack>,
+    steps: u64,
+    furthest: usize,
+    elapsed: std::time::Duration
+}
+
+impl Machine {
+    pub fn new(entry: usize) -> Machine {
+        return Machine{pc: entry, pos: 0, calls: vec![0], backtrack: Vec::new(), steps: 0, furthest: 0, elapsed: std::time::Duration::ZERO}
+    }
+
+    // The earliest place in the input the match can still go back to, nothing
+    // before it is ever looked at again
+    pub fn oldest(&self) -> usize {
+        return self.backtrack.first().map_or(self.pos, |entry| entry.pos.min(self.pos))
+    }
+
+    // Runs on `input`, which is the whole input from byte `base` on, and `base`
+    // can't be past `oldest()`. Unless the input is `finished`, an instruction
+    // that would need to see past its end stops the run instead of deciding.
+    // Only time spent running counts towards the timeout
+    pub fn resume(&mut self, program: &compiler::Program, input: &str, base: usize, finished: bool) -> Outcome {
+        let started = std::time::Instant::now();
+        let res = self.step(program, input, base, finished, started);
+        self.elapsed += started.elapsed();
+        return res
+    }
+
+    fn step(&mut self, program: &compiler::Program, input: &str, base: usize, finished: bool, started: std::time::Instant) -> Outcome {
+        let options = &program.options;
+        // Polling only comes round every so often, which a short match never gets to
+        if options.cancel.as_ref().is_some_and(|cancel| cancel.is_cancelled()) {
+            return Outcome::Done(Err(interpreter::cancelled_error(input, self.furthest - base)))
+        };
+        loop {
+            let rest = &input[self.pos - base..];
+            if !finished && needs_more(&program.instructions[self.pc], rest) {
+                return Outcome::NeedMoreInput
+            };
+            self.steps += 1;
+            self.furthest = self.furthest.max(self.pos);
+            if self.steps.is_multiple_of(POLL_INTERVAL) && options.cancel.as_ref().is_some_and(|cancel| cancel.is_cancelled()) {
+                // Whatever came before `base` is gone, so this is where in what's left
+                return Outcome::Done(Err(interpreter::cancelled_error(input, self.furthest - base)))
+            };
+            let exceeded = if options.max_steps.is_some_and(|max| self.steps > max) {
+                Some((context::Limit::Steps, format!("Took more than {} steps", options.max_steps.unwrap_or(0))))
+            } else if options.timeout.is_some_and(|timeout| self.steps.is_multiple_of(POLL_INTERVAL) && self.elapsed + started.elapsed() > timeout) {
+                Some((context::Limit::Time, format!("Ran for longer than {:?}", options.timeout.unwrap_or_default())))
+            } else {
+                None
+            };
+            if let Some((limit, message)) = exceeded {
+                let (pos_start, pos_end) = span(program, self.pc);
+                return Outcome::Done(Err(interpreter::limit_error(limit, message, pos_start, pos_end)))
+            };
+            let matched = match &program.instructions[self.pc] {
+                Instruction::Char(c, _) => match rest.chars().next() {
+                    Some(current) if current == *c => Some(c.len_utf8()),
+                    _ => None
+                },
+                Instruction::Literal(string, caseless, _) => parserCombinators::literal_match(string, *caseless, rest),
+                Instruction::Builtin(builtin, _, _) => builtin.matches(rest),
+                Instruction::Dispatch(table) => {
+                    self.pc = program.tables[*table].target(rest.chars().next());
+                    continue
+                },
+                Instruction::Choice(target) => {
+                    self.backtrack.push(Backtrack{pc: *target, pos: self.pos, calls: self.calls.len()});
+                    self.pc += 1;
+                    continue
+                },
+                Instruction::Commit(target) => {
+                    self.backtrack.pop();
+                    self.pc = *target;
+                    continue
+                },
+                Instruction::Call(target, span) => {
+                    // The entry rule is on `calls` too, so this is every definition being matched
+                    if self.calls.len() >= options.max_depth {
+                        let (pos_start, pos_end) = &program.spans[*span];
+                        return Outcome::Done(Err(interpreter::depth_error(options.max_depth, pos_start.copy(), pos_end.copy())))
+                    };
+                    self.calls.push(self.pc + 1);
+                    self.pc = *target;
+                    continue
+                },
+                Instruction::Return => {
+                    self.pc = match self.calls.pop() {
+                        Some(a) => a,
+                        None => panic!("Returned from the entry rule twice")
+                    };
+                    continue
+                },
+                Instruction::Fail(_) => None,
+                Instruction::End => return Outcome::Done(Ok(self.pos))
+            };
+            match matched {
+                Some(length) => {
+                    self.pos += length;
+                    self.pc += 1;
+                },
+                None => match self.backtrack.pop() {
+                    Some(entry) => {
+                        self.pc = entry.pc;
+                        self.pos = entry.pos;
+                        self.calls.truncate(entry.calls);
+                    },
+                    None => return Outcome::Done(Err(failure(program, self.pc, rest)))
+                }
+            }
+        }
+    }
+}
+
+// Whether what the instruction does with `rest` could change once more input
+// comes after it
+fn needs_more(instruction: &Instruction, rest: &str) -> bool {
+    match instruction {
+        Instruction::Char(_, _) | Instruction::Dispatch(_) => rest.is_empty(),
+        Instruction::Literal(string, false, _) => rest.len() < string.len() && string.starts_with(rest),
+        Instruction::Literal(string, true, _) => rest.chars().count() < string.chars().count(),
+        Instruction::Builtin(prelude::Builtin::Newline, _, _) => rest.is_empty() || rest == "\r",
+        Instruction::Builtin(_, _, _) => rest.is_empty(),
+        _ => false
+    }
+}
+
+// Runs the program from `entry` on the input from byte `start` on and returns the byte
+// the match ends at. Address 0 is always `End`, so returning from the entry rule finishes the match
+pub fn run(program: &compiler::Program, entry: usize, input: &str, start: usize) -> Result<usize, error::Error> {
+    let mut machine = Machine::new(entry);
+    machine.pos = start;
+    machine.furthest = start;
+    match machine.resume(program, input, 0, true) {
+        Outcome::Done(res) => res,
+        Outcome::NeedMoreInput => panic!("Ran out of input that was already finished")
+    }
+}
+
+// Where in the grammar the instruction at `pc` came from, if it remembers
+fn span(program: &compiler::Program, pc: usize) -> (position::Position, position::Position) {
+    match &program.instructions[pc] {
+        Instruction::Char(_, span) | Instruction::Literal(_, _, span) | Instruction::Builtin(_, _, span) | Instruction::Call(_, span) => {
+            let (pos_start, pos_end) = &program.spans[*span];
+            (pos_start.copy(), pos_end.copy())
+        },
+        _ => (program.pos_start.copy(), program.pos_end.copy())
+    }
+}
+
+// The error the interpreter would have given for the same failure, it's only
+// built once the match has failed for good since most failures get backtracked
+fn failure(program: &compiler::Program, pc: usize, left: &str) -> error::Error {
+    let input = (String::from(""), 0);
+    let res = match &program.instructions[pc] {
+        Instruction::Char(c, span) => {
+            let (pos_start, pos_end) = &program.spans[*span];
+            parserCombinators::literal_parse(c.to_string(), false, left, input, pos_start.copy(), pos_end.copy())
+        },
+        Instruction::Literal(string, caseless, span) => {
+            let (pos_start, pos_end) = &program.spans[*span];
+            parserCombinators::literal_parse(string.clone(), *caseless, left, input, pos_start.copy(), pos_end.copy())
+        },
+        Instruction::Builtin(builtin, name, span) => {
+            let (pos_start, pos_end) = &program.spans[*span];
+            parserCombinators::builtin_parse(*builtin, name.clone(), left, input, pos_start.copy(), pos_end.copy())
+        },
+        Instruction::Fail(index) => Err(program.errors[*index].copy()),
+        _ => panic!("{} can't fail", program.instructions[pc])
+    };
+    match res {
+        Ok(_) => panic!("{} failed in the vm but not in the interpreter", program.instructions[pc]),
+        Err(err) => err
+    }
+}
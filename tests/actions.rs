@@ -0,0 +1,69 @@
+use ray_rengine::rayRengine::Grammar;
+use ray_rengine::actions::Actions;
+
+#[derive(Debug, PartialEq)]
+enum Calc {
+    Num(i64),
+    Op(char)
+}
+
+// Applies each operator in turn, left to right
+fn fold(children: Vec<Calc>) -> Calc {
+    let mut children = children.into_iter();
+    let mut total = match children.next() {
+        Some(Calc::Num(n)) => n,
+        other => panic!("expected a number, got {:?}", other)
+    };
+    while let (Some(Calc::Op(op)), Some(Calc::Num(n))) = (children.next(), children.next()) {
+        total = match op {
+            '+' => total + n,
+            '-' => total - n,
+            '*' => total * n,
+            _ => total / n
+        };
+    }
+    Calc::Num(total)
+}
+
+fn calculator() -> (Grammar, Actions<Calc>) {
+    let grammar = Grammar::new("number := DIGIT+\naddop := '+' | '-'\nmulop := '*' | '/'\nexpr := term (addop term)*\nterm := factor (mulop factor)*\nfactor := '(' expr ')' | number\nmain := expr".to_string()).unwrap();
+    let mut actions = Actions::new();
+    actions.on("number", |text, _| Calc::Num(text.parse().unwrap()))
+        .on("addop", |text, _| Calc::Op(text.chars().next().unwrap()))
+        .on("mulop", |text, _| Calc::Op(text.chars().next().unwrap()))
+        .on("expr", |_, children| fold(children))
+        .on("term", |_, children| fold(children));
+    (grammar, actions)
+}
+
+#[test]
+fn readme_example() {
+    let (grammar, actions) = calculator();
+    assert_eq!(grammar.evaluate(&actions, "18*(1-2)".to_string()).unwrap(), vec![Calc::Num(-18)]);
+    assert_eq!(grammar.evaluate(&actions, "12+3*2".to_string()).unwrap(), vec![Calc::Num(18)]);
+    assert_eq!(grammar.evaluate(&actions, "12+3)".to_string()).unwrap_err().name, "InputError");
+}
+
+#[test]
+fn deep_nesting() {
+    let (grammar, actions) = calculator();
+    let input = format!("{}1{}", "(".repeat(20000), ")".repeat(20000));
+    assert_eq!(grammar.evaluate(&actions, input).unwrap(), vec![Calc::Num(1)]);
+}
+
+#[test]
+fn rules_without_an_action_hand_values_up() {
+    let grammar = Grammar::new("digit := DIGIT\npair := digit digit\nmain := pair+".to_string()).unwrap();
+    let mut actions = Actions::new();
+    actions.on("digit", |text, _| text.parse::<u32>().unwrap());
+    assert_eq!(grammar.evaluate(&actions, "1234".to_string()).unwrap(), vec![1, 2, 3, 4]);
+    assert_eq!(grammar.evaluate_rule("pair", &actions, "56".to_string()).unwrap(), vec![5, 6]);
+}
+
+#[test]
+fn backtracking_leaves_nothing() {
+    let grammar = Grammar::new("a := 'x'\nb := 'y'\nmain := a a b | a a 'z' | a".to_string()).unwrap();
+    let tree = grammar.parse("xxz".to_string()).unwrap();
+    assert_eq!(tree.children.iter().map(|child| child.rule.as_str()).collect::<Vec<_>>(), vec!["a", "a"]);
+    assert_eq!((tree.start, tree.end), (0, 3));
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use common::{Rng, grammar, input};
+use ray_rengine::analysis::{GrammarAnalysis, Ranges};
+use ray_rengine::rayRengine::Grammar;
+use ray_rengine::tree::Tree;
+
+fn has(ranges: &Ranges, c: char) -> bool {
+    ranges.iter().any(|(lo, hi)| *lo <= c as u32 && c as u32 <= *hi)
+}
+
+#[test]
+fn readme_example() {
+    let grammar = Grammar::new("main := list EOI\nlist := '[' items? ']'\nitems := item (',' item)*\nitem := DIGIT+ | list".to_string()).unwrap();
+    let analysis = grammar.analysis();
+    assert_eq!(analysis.nullable("item"), Some(false));
+    let first = analysis.first("item").unwrap();
+    assert!("0123456789[".chars().all(|c| has(first, c)));
+    assert!(!has(first, ']'));
+    let follow = analysis.follow("item").unwrap();
+    assert!(has(&follow.chars, ',') && has(&follow.chars, ']'));
+    assert!(!has(&follow.chars, '0') && !follow.end);
+    assert!(analysis.follow("main").unwrap().end);
+    assert_eq!(analysis.nullable("missing"), None);
+}
+
+#[test]
+fn other_entry_rules() {
+    let grammar = Grammar::new("key := ALPHA+\nvalue := key ':'\na := 'x'\nmain := a".to_string()).unwrap();
+    // Nothing from `main` ever gets to `value`
+    let follow = grammar.analysis().follow("value").unwrap().clone();
+    assert!(follow.chars.is_empty() && !follow.end);
+    let analysis = grammar.analysis_rule("value");
+    assert!(analysis.follow("value").unwrap().end);
+    let follow = analysis.follow("key").unwrap();
+    assert!(has(&follow.chars, ':') && !has(&follow.chars, 'a') && !follow.end);
+    assert!(!analysis.follow("main").unwrap().end);
+}
+
+#[test]
+fn literals() {
+    let grammar = Grammar::new("kw := 'if' | 'else' | i'then'\nmain := kw".to_string()).unwrap();
+    let analysis = grammar.analysis();
+    let literals = &analysis.rules["kw"].literals;
+    assert!(literals.contains("if") && literals.contains("else"), "{:?}", literals);
+}
+
+// Every tree of a parameterless definition in a real match has to fit what the
+// analysis says about that definition
+fn check(tree: &Tree, input: &str, analysis: &GrammarAnalysis, source: &str) {
+    if let Some(rule) = analysis.rules.get(&tree.rule) {
+        match input[tree.start..tree.end].chars().next() {
+            Some(c) => assert!(has(&rule.first, c), "first of {} {:?}\n{}", tree.rule, input, source),
+            None => assert!(rule.nullable, "nullable {} {:?}\n{}", tree.rule, input, source)
+        };
+        match input[tree.end..].chars().next() {
+            Some(c) => assert!(has(&rule.follow.chars, c), "follow of {} {:?} at {}\n{}", tree.rule, input, tree.end, source),
+            None => assert!(rule.follow.end, "end after {} {:?}\n{}", tree.rule, input, source)
+        }
+    };
+    tree.children.iter().for_each(|child| check(child, input, analysis, source));
+}
+
+#[test]
+fn never_too_little() {
+    let mut rng = Rng(0x9e3779b97f4a7c15);
+    for _ in 0..1000 {
+        let source = grammar(&mut rng);
+        let grammar = Grammar::new(source.clone()).unwrap();
+        let analysis = grammar.analysis();
+        for _ in 0..20 {
+            let text = input(&mut rng);
+            if grammar.is_match(text.clone()).unwrap() {
+                check(&grammar.parse(text.clone()).unwrap(), &text, &analysis, &source);
+            }
+        }
+    }
+}
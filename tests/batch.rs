@@ -0,0 +1,69 @@
+mod common;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use common::{Rng, grammar, input};
+use ray_rengine::rayRengine::{Grammar, Engine};
+use ray_rengine::executor::{Executor, Job};
+
+// Runs every job on the thread that asked, and counts the batches it was given
+struct OneAtATime(AtomicUsize);
+
+impl Executor for OneAtATime {
+    fn workers(&self) -> usize {
+        3
+    }
+
+    fn execute<'j>(&self, jobs: Vec<Job<'j>>) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        jobs.into_iter().for_each(|job| job());
+    }
+}
+
+#[test]
+fn same_order_as_one_at_a_time() {
+    let mut rng = Rng(0x1234567);
+    for i in 0..200 {
+        let source = grammar(&mut rng);
+        let mut grammar = Grammar::new(source).unwrap();
+        if i % 2 == 0 {
+            grammar.set_engine(Engine::Vm).unwrap();
+        };
+        let inputs: Vec<String> = (0..50).map(|_| input(&mut rng)).collect();
+        let expected: Vec<_> = inputs.iter().map(|text| grammar.is_match(text.clone()).map_err(|err| err.to_string())).collect();
+        let batch: Vec<_> = grammar.is_match_all_rule("main", inputs.clone(), 1 + i % 7).into_iter().map(|res| res.map_err(|err| err.to_string())).collect();
+        assert_eq!(expected, batch);
+        let batch: Vec<_> = grammar.is_match_all(inputs).into_iter().map(|res| res.map_err(|err| err.to_string())).collect();
+        assert_eq!(expected, batch);
+    }
+}
+
+#[test]
+fn any_iterator() {
+    let grammar = Grammar::new("main := DIGIT+".to_string()).unwrap();
+    let results = grammar.is_match_all((0..1000).map(|n| if n % 3 == 0 { format!("x{}", n) } else { n.to_string() }));
+    assert_eq!(results.len(), 1000);
+    assert!(results.iter().enumerate().all(|(n, res)| *res.as_ref().unwrap() == (n % 3 != 0)));
+    assert!(grammar.is_match_all(Vec::new()).is_empty());
+}
+
+#[test]
+fn own_executor() {
+    let grammar = Grammar::new("main := DIGIT+".to_string()).unwrap();
+    let executor = OneAtATime(AtomicUsize::new(0));
+    for _ in 0..3 {
+        let results = grammar.is_match_all_with("main", (0..100).map(|n| if n % 4 == 0 { format!("{}x", n) } else { n.to_string() }), &executor);
+        assert!(results.iter().enumerate().all(|(n, res)| *res.as_ref().unwrap() == (n % 4 != 0)));
+    }
+    assert_eq!(executor.0.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn shared_between_threads() {
+    let grammar = Arc::new(Grammar::new("main := '(' main ')' | 'x'".to_string()).unwrap());
+    let handles: Vec<_> = (0..4).map(|depth| {
+        let grammar = Arc::clone(&grammar);
+        std::thread::spawn(move || grammar.is_match(format!("{}x{}", "(".repeat(depth), ")".repeat(depth))).unwrap())
+    }).collect();
+    assert!(handles.into_iter().all(|handle| handle.join().unwrap()));
+}
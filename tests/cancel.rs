@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+use ray_rengine::rayRengine::{Grammar, Engine};
+use ray_rengine::context::{MatchOptions, CancelToken};
+
+// Every level tries the one inside it three times over, which the interpreter
+// doesn't remember since `b` isn't the first thing matched
+const SLOW: &str = "w(x) := '-' x 'x' | '-' x 'y' | '-' x\nb := '(' w(b) ')' | 'z'\nmain := w(b)";
+
+fn grammar(source: &str, engine: Engine, token: &CancelToken) -> Grammar {
+    let mut grammar = Grammar::new(source.to_string()).unwrap();
+    grammar.set_engine(engine).unwrap();
+    grammar.set_options(MatchOptions{cancel: Some(token.clone()), ..MatchOptions::default()});
+    grammar
+}
+
+#[test]
+fn from_another_thread() {
+    let input = format!("{}z{}", "-(".repeat(40), ")".repeat(40));
+    for engine in [Engine::Interpreter, Engine::Vm] {
+        let token = CancelToken::new();
+        let grammar = grammar(SLOW, engine, &token);
+        let started = Instant::now();
+        let canceller = token.clone();
+        let err = std::thread::scope(|scope| {
+            scope.spawn(move || {
+                std::thread::sleep(Duration::from_millis(50));
+                canceller.cancel();
+            });
+            grammar.is_match(input.clone()).unwrap_err()
+        });
+        assert_eq!(err.name, "Cancelled", "{:?}", engine);
+        assert!(started.elapsed() < Duration::from_secs(10), "{:?}", engine);
+        // Somewhere in the input, not in the grammar
+        assert!(err.pos_start.index as usize <= input.len(), "{:?}", engine);
+    }
+}
+
+#[test]
+fn cancelled_before_starting() {
+    for engine in [Engine::Interpreter, Engine::Vm] {
+        let token = CancelToken::new();
+        token.cancel();
+        // Regular, so it's over in a step or two on either engine
+        let grammar = grammar("main := 'a' DIGIT", engine, &token);
+        let err = grammar.is_match("a1".to_string()).unwrap_err();
+        assert_eq!(err.name, "Cancelled", "{:?}", engine);
+        assert_eq!(grammar.search("xa1".to_string()).unwrap_err().name, "Cancelled", "{:?}", engine);
+    }
+}
+
+#[test]
+fn reset() {
+    for engine in [Engine::Interpreter, Engine::Vm] {
+        let token = CancelToken::new();
+        let grammar = grammar("main := x*\nx := '(' x ')' | 'a' | 'b'", engine, &token);
+        let input = "ab".repeat(10000);
+        token.cancel();
+        assert!(token.is_cancelled());
+        assert_eq!(grammar.is_match(input.clone()).unwrap_err().name, "Cancelled", "{:?}", engine);
+        token.reset();
+        assert!(grammar.is_match(input).unwrap(), "{:?}", engine);
+    }
+}
@@ -0,0 +1,24 @@
+mod common;
+
+use common::matches;
+
+#[test]
+fn strings() {
+    assert!(matches("main := i'select'", "SeLeCt"));
+    assert!(!matches("main := 'select'", "SELECT"));
+    assert!(matches("main := i\"abcdef\"+", "aBcF"));
+}
+
+#[test]
+fn whole_definition() {
+    let source = "^main := 'content-' type\ntype := 'type'";
+    assert!(matches(source, "CONTENT-type"));
+    // Definitions used from inside keep their own behaviour
+    assert!(!matches(source, "CONTENT-TYPE"));
+}
+
+#[test]
+fn unicode_folding() {
+    assert!(matches("^main := 'straße'", "STRAẞE"));
+    assert!(matches("main := i'σ'+", "Σς"));
+}
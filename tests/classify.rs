@@ -0,0 +1,39 @@
+use ray_rengine::analysis::Class;
+use ray_rengine::rayRengine::Grammar;
+
+fn class(source: &str, rule: &str) -> Class {
+    let grammar = Grammar::new(source.to_string()).unwrap();
+    grammar.classify().rules[rule]
+}
+
+#[test]
+fn readme_example() {
+    let source = "main := list EOI\nlist := '[' (list | num)* ']'\nnum := DIGIT+";
+    assert_eq!(class(source, "num"), Class::Regular);
+    assert_eq!(class(source, "list"), Class::ContextFree);
+    assert_eq!(class(source, "main"), Class::UsesPredicates);
+    assert_eq!(Grammar::new(source.to_string()).unwrap().classify().grammar, Class::UsesPredicates);
+}
+
+#[test]
+fn includes_what_it_reaches() {
+    // main never gets back to itself, a does
+    let source = "main := a\na := a 'x' | 'y'";
+    assert_eq!(class(source, "a"), Class::LeftRecursive);
+    assert_eq!(class(source, "main"), Class::LeftRecursive);
+    let source = "main := paren\nparen := '(' paren? ')'";
+    assert_eq!(class(source, "main"), Class::ContextFree);
+}
+
+#[test]
+fn parameters() {
+    assert_eq!(class("main := w(main) | 'a'\nw(x) := '(' x ')'", "main"), Class::ContextFree);
+    assert_eq!(class("main := w(main) | 'a'\nw(x) := x ')'", "main"), Class::LeftRecursive);
+}
+
+#[test]
+fn growing_arguments() {
+    // Twice as big every time, so only a few of them are looked at
+    assert_eq!(class("f(x) := 'a' f((x x)) | x\nmain := f('b')", "main"), Class::ContextFree);
+    assert_eq!(class("f(x) := f((x x)) 'a' | x\nmain := f('b')", "main"), Class::LeftRecursive);
+}
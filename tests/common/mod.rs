@@ -0,0 +1,106 @@
+// Random grammars and inputs, for checking that different ways of matching
+// agree with each other. Every run with the same seed makes the same ones
+#![allow(dead_code)]
+
+use ray_rengine::rayRengine::{Grammar, Engine};
+
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    pub fn pick<'a>(&mut self, from: &[&'a str]) -> &'a str {
+        from[self.below(from.len() as u64) as usize]
+    }
+}
+
+// An expression for rule `idx` out of `n`, along with whether it can match
+// nothing. Rules only use the ones after them, so there's no left recursion
+fn expr(rng: &mut Rng, depth: u32, idx: usize, n: usize, nullable: &[bool]) -> (String, bool) {
+    let choice = if depth == 0 { rng.below(4) } else { rng.below(9) };
+    match choice {
+        0 => {
+            let literal = rng.pick(&["'a'", "'b'", "'ab'", "'ba'", "''", "i'A'", "\"ab(\"", "'('", "')'"]);
+            (literal.to_string(), literal == "''")
+        },
+        1 => {
+            let name = rng.pick(&["DIGIT", "ALPHA", "ANY", "EOI", "rec", "w('a')", "w(b1)", "list(b1)"]);
+            (name.to_string(), name == "EOI")
+        },
+        2 | 3 => if idx + 1 < n {
+            let used = idx + 1 + rng.below((n - idx - 1) as u64) as usize;
+            (format!("r{}", used), nullable[used])
+        } else {
+            ("'a'".to_string(), false)
+        },
+        4 | 5 => {
+            let (left, left_nullable) = expr(rng, depth - 1, idx, n, nullable);
+            let (right, right_nullable) = expr(rng, depth - 1, idx, n, nullable);
+            (format!("({} {})", left, right), left_nullable && right_nullable)
+        },
+        6 => {
+            let (left, left_nullable) = expr(rng, depth - 1, idx, n, nullable);
+            let (right, right_nullable) = expr(rng, depth - 1, idx, n, nullable);
+            (format!("({} | {})", left, right), left_nullable || right_nullable)
+        },
+        _ => {
+            let (inner, inner_nullable) = expr(rng, depth - 1, idx, n, nullable);
+            // Repeating something that can match nothing is an error
+            let op = if inner_nullable { "?" } else { rng.pick(&["?", "*", "+"]) };
+            (format!("({}){}", inner, op), inner_nullable || op != "+")
+        }
+    }
+}
+
+pub fn grammar(rng: &mut Rng) -> String {
+    let n = 1 + rng.below(5) as usize;
+    let mut nullable = vec![false; n];
+    let mut rules = vec![String::new(); n];
+    for i in (0..n).rev() {
+        let (body, can_be_empty) = expr(rng, 3, i, n, &nullable);
+        nullable[i] = can_be_empty;
+        let modifier = rng.pick(&["^", "~", "", "", "", ""]);
+        rules[i] = format!("{}r{} := {}", modifier, i, body);
+    }
+    rules.push("rec := '(' rec ')' | 'a'".to_string());
+    rules.push("b1 := 'b'".to_string());
+    rules.push("skip := ' '".to_string());
+    rules.push("w(x) := '(' x ')' | x".to_string());
+    rules.push("list(x) := x (' ' list(x))?".to_string());
+    rules.push("main := r0".to_string());
+    rules.join("\n")
+}
+
+pub fn input(rng: &mut Rng) -> String {
+    let alphabet = ['a', 'b', '(', ')', '1', 'A', ' '];
+    let len = rng.below(8);
+    (0..len).map(|_| alphabet[rng.below(alphabet.len() as u64) as usize]).collect()
+}
+
+pub fn show(result: &Result<String, ray_rengine::error::Error>) -> String {
+    match result {
+        Ok(left) => format!("ok {:?}", left),
+        Err(err) => format!("err {}", err)
+    }
+}
+
+// Whether `source` matches all of `input`, after checking both engines agree
+pub fn matches(source: &str, input: &str) -> bool {
+    let mut results = [Engine::Interpreter, Engine::Vm].iter().map(|engine| {
+        let mut grammar = Grammar::new(source.to_string()).unwrap();
+        grammar.set_engine(*engine).unwrap();
+        grammar.is_match(input.to_string()).unwrap()
+    });
+    let first = results.next().unwrap();
+    assert!(results.all(|other| other == first), "{} {:?}", source, input);
+    first
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use std::collections::HashMap;
+use common::{Rng, grammar, input, show};
+use ray_rengine::rayRengine::Grammar;
+
+#[test]
+fn only_regular_definitions() {
+    let grammar = Grammar::new("num := DIGIT+ ('.' DIGIT+)?\nparen := '(' paren? ')'\nmain := num EOI".to_string()).unwrap();
+    assert!(grammar.context.dfas.contains_key("num"));
+    assert!(!grammar.context.dfas.contains_key("paren"));
+}
+
+#[test]
+fn same_as_without() {
+    let mut rng = Rng(77);
+    let mut compiled = 0;
+    for _ in 0..1000 {
+        let source = grammar(&mut rng);
+        let with = Grammar::new(source.clone()).unwrap();
+        let mut without = Grammar::new(source.clone()).unwrap();
+        without.context.dfas = HashMap::new();
+        let mut rules: Vec<&String> = with.context.symbols.keys().filter(|name| name.starts_with('r')).collect();
+        rules.sort();
+        compiled += rules.iter().filter(|rule| with.context.dfas.contains_key(**rule)).count();
+        for _ in 0..10 {
+            let text = input(&mut rng);
+            for rule in rules.iter() {
+                let expected = show(&without.string_left_rule(rule, text.clone()));
+                assert_eq!(expected, show(&with.string_left_rule(rule, text.clone())), "\n{}\nrule {} input {:?}", source, rule, text);
+            }
+        }
+    }
+    // Or there'd be nothing being checked
+    assert!(compiled > 500, "{}", compiled);
+}
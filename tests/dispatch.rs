@@ -0,0 +1,35 @@
+mod common;
+
+use common::show;
+use ray_rengine::compiler;
+use ray_rengine::rayRengine::{Grammar, Engine};
+use ray_rengine::vm::Instruction;
+
+#[test]
+fn choices_start_with_a_dispatch() {
+    let grammar = Grammar::new("op := '+' | '-' | '*' | '/'\nmain := op".to_string()).unwrap();
+    let program = compiler::compile(&grammar.ast).unwrap();
+    assert!(!program.tables.is_empty());
+    assert!(program.instructions.iter().any(|instruction| matches!(instruction, Instruction::Dispatch(_))));
+}
+
+#[test]
+fn same_results_as_trying_in_order() {
+    let sources = [
+        "main := ('ab' | 'a' | 'b')*",
+        "main := (i'c' | 'C' 'x' | 'é' | DIGIT | LETTER)+",
+        "main := ('a' | '' | 'b') 'b'",
+        "main := ('a' 'x' | 'a' | EOI) ANY?",
+        "main := (x | 'z')*\nx := 'zz' | 'y'"
+    ];
+    let inputs = ["", "a", "ab", "abba", "b", "Cx", "cC", "é1ж", "ax", "zzz", "zy", "b b"];
+    for source in sources {
+        let interpreter = Grammar::new(source.to_string()).unwrap();
+        let mut vm = Grammar::new(source.to_string()).unwrap();
+        vm.set_engine(Engine::Vm).unwrap();
+        for input in inputs {
+            let expected = show(&interpreter.string_left_rule("main", input.to_string()));
+            assert_eq!(expected, show(&vm.string_left_rule("main", input.to_string())), "{} {:?}", source, input);
+        }
+    }
+}
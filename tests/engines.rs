@@ -0,0 +1,50 @@
+mod common;
+
+use common::{Rng, grammar, input, show};
+use ray_rengine::rayRengine::{Grammar, Engine};
+
+#[test]
+fn vm_same_as_interpreter() {
+    let mut rng = Rng(0x9e3779b97f4a7c15);
+    for _ in 0..1000 {
+        let source = grammar(&mut rng);
+        let interpreter = Grammar::new(source.clone()).unwrap();
+        let mut vm = Grammar::new(source.clone()).unwrap();
+        vm.set_engine(Engine::Vm).unwrap();
+        for _ in 0..10 {
+            let text = input(&mut rng);
+            let expected = show(&interpreter.string_left_rule("main", text.clone()));
+            assert_eq!(expected, show(&vm.string_left_rule("main", text.clone())), "\n{}\ninput {:?}", source, text);
+        }
+    }
+}
+
+#[test]
+fn same_errors() {
+    let sources = [
+        "main := 'a' missing",
+        "w(x) := x missing\nmain := w('a')",
+        "main := DIGIT('a')",
+        "main := 'a' main",
+    ];
+    for source in sources {
+        let interpreter = Grammar::new(source.to_string()).unwrap();
+        let mut vm = Grammar::new(source.to_string()).unwrap();
+        vm.set_engine(Engine::Vm).unwrap();
+        for text in ["a", "aa", "b"] {
+            assert_eq!(show(&interpreter.string_left_rule("main", text.to_string())), show(&vm.string_left_rule("main", text.to_string())), "{} {:?}", source, text);
+        }
+    }
+}
+
+#[test]
+fn switching_back() {
+    let mut grammar = Grammar::new("main := '(' main ')' | 'x'".to_string()).unwrap();
+    assert_eq!(grammar.engine(), Engine::Interpreter);
+    grammar.set_engine(Engine::Vm).unwrap();
+    assert_eq!(grammar.engine(), Engine::Vm);
+    assert!(grammar.is_match("((x))".to_string()).unwrap());
+    grammar.set_engine(Engine::Interpreter).unwrap();
+    assert_eq!(grammar.engine(), Engine::Interpreter);
+    assert!(!grammar.is_match("((x)".to_string()).unwrap());
+}
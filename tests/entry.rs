@@ -0,0 +1,31 @@
+use ray_rengine::rayRengine::{Grammar, Engine};
+
+const ENGINES: [Engine; 2] = [Engine::Interpreter, Engine::Vm];
+
+const SOURCE: &str = "key := \"abcdefghijklmnopqrstuvwxyz\"+\nobject := '{' (key ':' key)* '}'\nmain := object";
+
+#[test]
+fn readme_example() {
+    for engine in ENGINES {
+        let mut grammar = Grammar::new(SOURCE.to_string()).unwrap();
+        grammar.set_engine(engine).unwrap();
+        assert!(grammar.is_match("{a:b}".to_string()).unwrap(), "{:?}", engine);
+        assert!(grammar.is_match_rule("key", "abc".to_string()).unwrap(), "{:?}", engine);
+        assert!(!grammar.is_match_rule("key", "{a:b}".to_string()).unwrap(), "{:?}", engine);
+        assert_eq!(grammar.search_rule("object", "x {a:b}".to_string()).unwrap(), Some((2, 7)), "{:?}", engine);
+        assert_eq!(grammar.is_match_rule("array", "[]".to_string()).unwrap_err().name, "NoDefinitionError", "{:?}", engine);
+    }
+}
+
+#[test]
+fn without_main() {
+    let grammar = Grammar::new("key := 'a'+".to_string()).unwrap();
+    assert!(grammar.is_match_rule("key", "aaa".to_string()).unwrap());
+    assert_eq!(grammar.is_match("aaa".to_string()).unwrap_err().name, "NoDefinitionError");
+}
+
+#[test]
+fn rule_with_parameters() {
+    let grammar = Grammar::new("w(x) := '(' x ')'\nmain := w('a')".to_string()).unwrap();
+    assert!(grammar.is_match_rule("w", "(a)".to_string()).is_err());
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use common::{Rng, grammar, input};
+use ray_rengine::formatter::format;
+use ray_rengine::rayRengine::Grammar;
+
+// Errors point somewhere else in the formatted grammar, so only what they say counts
+fn result(grammar: &Grammar, text: &str) -> Result<String, (String, String)> {
+    grammar.string_left_rule("main", text.to_string()).map_err(|err| (err.name, err.message))
+}
+
+#[test]
+fn readme_example() {
+    let formatted = format("nums:=\"0123456789\"\nexpr := (term) ((('+'|'-')) term)*   # sums\n").unwrap();
+    assert_eq!(formatted, "nums := \"0123456789\"\nexpr := term (\"+-\" term)* # sums\n");
+}
+
+#[test]
+fn lines_up_and_keeps_comments() {
+    let source = "# numbers\nnums := \"0123456789\"   # digits\n   expr := (term) ((('+'|'-')) term)*\n~term := factor (('*'|'/') factor)* # products\n@~^factor := '(' expr ')' | nums+\n\n\nsep_by(item, sep) := item (sep item)*\nmain := sep_by(expr, ',')\n# the end\n";
+    let formatted = format(source).unwrap();
+    assert_eq!(formatted, "# numbers
+nums      := \"0123456789\" # digits
+expr      := term (\"+-\" term)*
+~term     := factor (\"*/\" factor)* # products
+^~@factor := '(' expr ')' | nums+
+
+sep_by(item, sep) := item (sep item)*
+main              := sep_by(expr, ',')
+# the end
+");
+    assert_eq!(format(&formatted).unwrap(), formatted);
+}
+
+#[test]
+fn matches_the_same() {
+    let mut rng = Rng(0x5eed);
+    for _ in 0..500 {
+        let source = grammar(&mut rng);
+        let formatted = format(&source).unwrap();
+        assert_eq!(format(&formatted).unwrap(), formatted, "\n{}", source);
+        let original = Grammar::new(source.clone()).unwrap();
+        let rewritten = Grammar::new(formatted.clone()).unwrap();
+        for _ in 0..10 {
+            let text = input(&mut rng);
+            assert_eq!(result(&original, &text), result(&rewritten, &text), "\n{}\n{}\ninput {:?}", source, formatted, text);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::time::Duration;
+use ray_rengine::rayRengine::{Grammar, Engine};
+use ray_rengine::context::{MatchOptions, CancelToken, Limit};
+
+const ENGINES: [Engine; 2] = [Engine::Interpreter, Engine::Vm];
+
+fn grammar(source: &str, engine: Engine, options: MatchOptions) -> Grammar {
+    let mut grammar = Grammar::new(source.to_string()).unwrap();
+    grammar.set_engine(engine).unwrap();
+    grammar.set_options(options);
+    grammar
+}
+
+fn error_name(grammar: &Grammar, input: &str) -> String {
+    match grammar.is_match(input.to_string()) {
+        Ok(matched) => panic!("finished with {}", matched),
+        Err(err) => err.name
+    }
+}
+
+fn limit(grammar: &Grammar, input: &str) -> Option<Limit> {
+    match grammar.is_match(input.to_string()) {
+        Ok(matched) => panic!("finished with {}", matched),
+        Err(err) => {
+            assert_eq!(err.name, "ResourceLimitExceeded");
+            assert!(err.message.starts_with(&format!("{}: ", err.limit().unwrap().option())), "{}", err.message);
+            err.limit()
+        }
+    }
+}
+
+#[test]
+fn depth_counts_definitions_being_matched_on_both_engines() {
+    // `main` once for every parenthesis, and once more for the x
+    let source = "main := '(' main ')' | 'x'";
+    for engine in ENGINES {
+        let grammar = grammar(source, engine, MatchOptions{max_depth: 5, ..MatchOptions::default()});
+        assert!(grammar.is_match("((((x))))".to_string()).unwrap(), "{:?}", engine);
+        assert_eq!(limit(&grammar, "(((((x)))))"), Some(Limit::Depth), "{:?}", engine);
+    }
+}
+
+#[test]
+fn default_depth_allows_thousands_of_parenthesis() {
+    let source = "skip := WS\nnums := DIGIT+\n~expr := term (('+' | '-') term)*\n~term := factor (('*' | '/') factor)*\n~factor := '(' expr ')' | nums\nmain := expr";
+    let input = format!("{}1{}", "(".repeat(5000), ")".repeat(5000));
+    for engine in ENGINES {
+        let grammar = grammar(source, engine, MatchOptions::default());
+        assert!(grammar.is_match(input.clone()).unwrap(), "{:?}", engine);
+    }
+}
+
+#[test]
+fn each_limit_says_which_it_was() {
+    let source = "x := 'a' x | 'b' | x x\nmain := (x | 'c')*";
+    let input = "a".repeat(3000) + "b";
+    for engine in ENGINES {
+        let steps = grammar(source, engine, MatchOptions{max_steps: Some(1000), ..MatchOptions::default()});
+        assert_eq!(limit(&steps, &input), Some(Limit::Steps));
+        let depth = grammar(source, engine, MatchOptions{max_depth: 10, ..MatchOptions::default()});
+        assert_eq!(limit(&depth, &input), Some(Limit::Depth));
+        let time = grammar(source, engine, MatchOptions{timeout: Some(Duration::ZERO), ..MatchOptions::default()});
+        assert_eq!(limit(&time, &input), Some(Limit::Time));
+        let token = CancelToken::new();
+        token.cancel();
+        let cancelled = grammar(source, engine, MatchOptions{cancel: Some(token), ..MatchOptions::default()});
+        assert_eq!(error_name(&cancelled, &input), "Cancelled");
+        assert_eq!(cancelled.is_match(input.clone()).unwrap_err().limit(), None);
+    }
+}
+
+#[test]
+fn memo_limit_only_bounds_the_interpreter() {
+    // Without a memo every level tries the one inside it three times over
+    let source = "a := b 'x' | b 'y' | b\nb := '(' a ')' | 'z'\nmain := a*";
+    let input = format!("{}z{}", "(".repeat(8), ")".repeat(8));
+    let options = MatchOptions{max_memo_bytes: Some(1000), ..MatchOptions::default()};
+    let interpreter = grammar(source, Engine::Interpreter, options.clone());
+    assert_eq!(limit(&interpreter, &input), Some(Limit::MemoBytes));
+    let vm = grammar(source, Engine::Vm, options);
+    assert!(vm.is_match(input).unwrap());
+}
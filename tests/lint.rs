@@ -0,0 +1,41 @@
+use ray_rengine::rayRengine::Grammar;
+
+// The names and messages of every warning for the grammar
+fn lint(source: &str) -> Vec<(String, String)> {
+    let grammar = Grammar::new(source.to_string()).unwrap();
+    grammar.lint().into_iter().map(|warning| (warning.name, warning.message)).collect()
+}
+
+fn names(source: &str) -> Vec<String> {
+    lint(source).into_iter().map(|(name, _)| name).collect()
+}
+
+#[test]
+fn readme_example() {
+    let warnings = lint("main := keyword | ident\nkeyword := 'if' | 'iff'\nident := (ALPHA)+\ndigits := DIGIT*\nsign := ('+' | '-')?");
+    let names: Vec<&str> = warnings.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["UnreachableAlternative", "RedundantParentheses", "UnusedRule", "UnusedRule", "UseCharacterSet"]);
+    assert_eq!(warnings[0].1, "'iff' never gets a chance, 'if' before it matches first; put 'iff' first");
+}
+
+#[test]
+fn optional_on_a_rule_that_can_match_nothing() {
+    assert_eq!(names("digits := DIGIT*\nmain := digits? 'x'"), ["RedundantOptional"]);
+    assert_eq!(names("digits := DIGIT+\nmain := digits? 'x'"), Vec::<String>::new());
+    assert_eq!(names("main := (' '*)? 'x'"), ["RedundantOptional"]);
+}
+
+#[test]
+fn optional_on_something_that_looks_ahead_is_kept() {
+    assert_eq!(names("end := EOI\nmain := 'x' end?"), Vec::<String>::new());
+    assert_eq!(names("w(x) := x*\nmain := w('a')? 'x'"), Vec::<String>::new());
+}
+
+#[test]
+fn skipping_rules_are_linted_as_written() {
+    let warnings = lint("skip := ' '\n~main := 'a' | 'a' 'b'");
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].1, "'a' 'b' never gets a chance, 'a' before it matches first; put 'a' 'b' first");
+    // `a b` only matches the second alternative
+    assert_eq!(names("skip := ' '\n~main := 'ab' | 'a' 'b'"), Vec::<String>::new());
+}
@@ -0,0 +1,52 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use ray_rengine::rayRengine::{Grammar, Engine};
+use ray_rengine::context::Limit;
+
+// Keeps track of the most that was ever allocated at once
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let now = ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+        PEAK.fetch_max(now, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+// Most a match running into the depth limit is allowed to use
+const BOUND: usize = 64 * 1024 * 1024;
+
+// Left recursion never gets anywhere, it nests until the depth limit stops it.
+// Everything it had on the way down has to fit in a bounded amount of memory,
+// however long the grammar's source is
+#[test]
+fn left_recursion_runs_into_the_depth_limit_in_bounded_memory() {
+    let comment = format!("# {}\n", "x".repeat(2048));
+    let source = format!("{}a := a 'x' | 'y'\nmain := a", comment);
+    let mut grammar = Grammar::new(source).unwrap();
+    for engine in [Engine::Interpreter, Engine::Vm] {
+        grammar.set_engine(engine).unwrap();
+        let before = ALLOCATED.load(Ordering::SeqCst);
+        PEAK.store(before, Ordering::SeqCst);
+        let err = match grammar.is_match("yxx".to_string()) {
+            Ok(matched) => panic!("{:?} matched {}", engine, matched),
+            Err(err) => err
+        };
+        let used = PEAK.load(Ordering::SeqCst) - before;
+        assert_eq!(err.name, "ResourceLimitExceeded");
+        assert_eq!(err.limit(), Some(Limit::Depth));
+        assert!(used < BOUND, "{:?} used {} bytes", engine, used);
+    }
+}
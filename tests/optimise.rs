@@ -0,0 +1,51 @@
+use ray_rengine::rayRengine::Grammar;
+use ray_rengine::optimiser;
+
+const GRAMMARS: [&str; 2] = ["main := expr
+expr := number op number
+op := '+' | '-'
+@number := DIGIT+", "skip := ' '
+_digits := DIGIT+
+@number := _digits
+op := '+' | '-'
+_sign := '-'?
+~expr := _sign number (op _sign number)*
+main := expr"];
+
+#[test]
+fn optimised_grammar_keeps_the_same_trees() {
+    for grammar in GRAMMARS {
+        let plain = Grammar::new(grammar.to_string()).unwrap();
+        let mut optimised = Grammar::new(grammar.to_string()).unwrap();
+        optimised.optimise().unwrap();
+        for input in ["1+23", "1 + 23", "-4-5 + 6", "7", "1 +", ""] {
+            let before = plain.parse(input.to_string()).map(|tree| tree.to_sexp(input));
+            let after = optimised.parse(input.to_string()).map(|tree| tree.to_sexp(input));
+            match (before, after) {
+                (Ok(before), Ok(after)) => assert_eq!(before, after, "{}", input),
+                (Err(_), Err(_)) => (),
+                (before, after) => panic!("{}: {:?} against {:?}", input, before.is_ok(), after.is_ok())
+            }
+        }
+    }
+}
+
+#[test]
+fn silent_definitions_get_inlined() {
+    let mut grammar = Grammar::new("_a := 'a'\nb := 'b'\nmain := _a b".to_string()).unwrap();
+    let report = grammar.optimise().unwrap();
+    assert_eq!(report.inlined, 1);
+    let dump = optimiser::dump(&grammar.context);
+    assert!(dump.contains("main := 'a' b\n"), "{}", dump);
+}
+
+#[test]
+fn literals_are_factored() {
+    let mut grammar = Grammar::new("main := 'break' | 'begin' | 'bool'".to_string()).unwrap();
+    grammar.optimise().unwrap();
+    for input in ["break", "begin", "bool"] {
+        assert!(grammar.is_match(input.to_string()).unwrap());
+    }
+    assert!(!grammar.is_match("bo".to_string()).unwrap());
+    assert!(optimiser::dump(&grammar.context).contains("main := 'b' ('reak' | 'egin' | 'ool')"));
+}
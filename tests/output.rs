@@ -0,0 +1,43 @@
+use ray_rengine::rayRengine::Grammar;
+
+const SOURCE: &str = "item := ALPHA+ | '\"' | '\\'\nlist := '[' item (',' item)* ']'\nmain := list";
+
+#[test]
+fn sexp() {
+    let input = "[ab,\",\\]";
+    let tree = Grammar::new(SOURCE.to_string()).unwrap().parse(input.to_string()).unwrap();
+    assert_eq!(tree.to_sexp(input), r#"(main 0 8 (list 0 8 (item 1 3 "ab") (item 4 5 "\"") (item 6 7 "\\")))"#);
+}
+
+#[test]
+fn json() {
+    let input = "[\"]";
+    let tree = Grammar::new(SOURCE.to_string()).unwrap().parse_rule("list", input.to_string()).unwrap();
+    assert_eq!(tree.to_json(input), r#"{
+  "rule": "list",
+  "start": 0,
+  "end": 3,
+  "text": "[\"]",
+  "children": [
+    {
+      "rule": "item",
+      "start": 1,
+      "end": 2,
+      "text": "\"",
+      "children": []
+    }
+  ]
+}"#);
+}
+
+#[test]
+fn captures() {
+    let input = "[ab,\",\\]";
+    let tree = Grammar::new(SOURCE.to_string()).unwrap().parse(input.to_string()).unwrap();
+    assert_eq!(tree.captures()["item"].len(), 3);
+    assert_eq!(tree.captures_json(input), r#"{
+  "item": [{"start": 1, "end": 3, "text": "ab"}, {"start": 4, "end": 5, "text": "\""}, {"start": 6, "end": 7, "text": "\\"}],
+  "list": [{"start": 0, "end": 8, "text": "[ab,\",\\]"}],
+  "main": [{"start": 0, "end": 8, "text": "[ab,\",\\]"}]
+}"#);
+}
@@ -0,0 +1,98 @@
+use ray_rengine::rayRengine::{Grammar, Engine};
+
+const ENGINES: [Engine; 2] = [Engine::Interpreter, Engine::Vm];
+
+fn grammar(source: &str, engine: Engine) -> Grammar {
+    let mut grammar = Grammar::new(source.to_string()).unwrap();
+    grammar.set_engine(engine).unwrap();
+    grammar
+}
+
+#[test]
+fn readme_example() {
+    let source = "whitespace := ' ' | '\\t' | '\\n'\nnums := '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' | '0'\nsep_by(item, sep) := item (whitespace* sep whitespace* item)*\nmain := '[' sep_by(nums+, ',') ']'";
+    for engine in ENGINES {
+        let grammar = grammar(source, engine);
+        assert!(grammar.is_match("[1, 23 ,4]".to_string()).unwrap(), "{:?}", engine);
+        assert!(!grammar.is_match("[1,,2]".to_string()).unwrap(), "{:?}", engine);
+    }
+}
+
+#[test]
+fn parameters_shadow_definitions() {
+    let source = "x := 'b'\nw(x) := '(' x ')'\nmain := w('a') x";
+    for engine in ENGINES {
+        let grammar = grammar(source, engine);
+        assert!(grammar.is_match("(a)b".to_string()).unwrap(), "{:?}", engine);
+        assert!(!grammar.is_match("(b)b".to_string()).unwrap(), "{:?}", engine);
+    }
+}
+
+#[test]
+fn wrong_number_of_arguments() {
+    // Even where `|` or `*` would have moved on from the error once matching
+    let sources = [
+        "w(x) := '(' x ')'\nmain := w('a', 'b')",
+        "w(x) := '(' x ')'\nmain := 'a' | w('a', 'b')",
+        "w(x) := '(' x ')'\nmain := 'a' w*",
+        "w(x) := '(' x ')'\nv(y) := y | w(y, y)\nmain := 'a'",
+        "main := 'a' | DIGIT('a', 'b')",
+    ];
+    for source in sources {
+        match Grammar::new(source.to_string()) {
+            Ok(_) => panic!("{} was accepted", source),
+            Err(err) => assert_eq!(err.name, "ArgumentError", "{}", source)
+        }
+    }
+    // A parameter shadows the definition it's named after
+    assert!(Grammar::new("w(x) := '(' x ')'\nv(w) := w\nmain := v('a')".to_string()).is_ok());
+}
+
+#[test]
+fn parentheses_after_a_rule_without_parameters() {
+    // Written before rules could take parameters, `t(` only starts a call when `t` takes some
+    let source = "t := 'x'\nmain := t(('*' | '/') t)*";
+    for engine in ENGINES {
+        let grammar = grammar(source, engine);
+        assert!(grammar.is_match("x*x".to_string()).unwrap(), "{:?}", engine);
+        assert!(grammar.is_match("x*x/x".to_string()).unwrap(), "{:?}", engine);
+        assert!(grammar.is_match("x".to_string()).unwrap(), "{:?}", engine);
+        assert!(!grammar.is_match("x*".to_string()).unwrap(), "{:?}", engine);
+    }
+    let grammar = grammar("main := ALPHA(DIGIT)+", Engine::Interpreter);
+    assert!(grammar.is_match("a12".to_string()).unwrap());
+}
+
+#[test]
+fn passing_parameters_on() {
+    // The same copy of the rule every time, however deep it goes
+    for source in ["sep_by(item, sep) := item (sep sep_by(item, sep))?\nmain := sep_by('a', ',')", "f(x, y) := x f(y, x) | EOI\nmain := f('a', ',')"] {
+        for engine in ENGINES {
+            let grammar = grammar(source, engine);
+            assert!(grammar.is_match("a,a,a".to_string()).unwrap(), "{:?} {}", engine, source);
+            assert!(!grammar.is_match("a,,a".to_string()).unwrap(), "{:?} {}", engine, source);
+        }
+    }
+}
+
+#[test]
+fn growing_arguments_only_on_the_interpreter() {
+    let source = "f(x) := 'a' f((x x)) | x\nmain := f('b')";
+    let mut grammar = Grammar::new(source.to_string()).unwrap();
+    assert!(grammar.is_match("b".to_string()).unwrap());
+    assert!(grammar.is_match("aabbbb".to_string()).unwrap());
+    assert!(!grammar.is_match("aabbb".to_string()).unwrap());
+    let err = grammar.set_engine(Engine::Vm).unwrap_err();
+    assert_eq!(err.name, "CompileError");
+    assert_eq!(grammar.engine(), Engine::Interpreter);
+
+    // Through another rule, and growing by a bit at a time
+    let source = "f(x) := 'a' g(x) | x\ng(y) := f((y 'b'))\nmain := f('b')";
+    let mut grammar = Grammar::new(source.to_string()).unwrap();
+    assert!(grammar.is_match("aabbb".to_string()).unwrap());
+    assert_eq!(grammar.set_engine(Engine::Vm).unwrap_err().name, "CompileError");
+
+    // Only matters when something uses it
+    let mut grammar = Grammar::new("f(x) := 'a' f((x x)) | x\nmain := 'b'".to_string()).unwrap();
+    assert!(grammar.set_engine(Engine::Vm).is_ok());
+}
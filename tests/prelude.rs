@@ -0,0 +1,46 @@
+mod common;
+
+use common::matches;
+
+#[test]
+fn each_rule() {
+    let cases = [
+        ("DIGIT", "7", "a"),
+        ("ALPHA", "Q", "1"),
+        ("ALNUM", "z", "_"),
+        ("HEXDIG", "F", "g"),
+        ("WS", "\t", "x"),
+        ("NEWLINE", "\r\n", " "),
+        ("ANY", "é", ""),
+        ("LETTER", "ж", "1"),
+        ("UPPERCASE", "Ж", "ж"),
+        ("LOWERCASE", "ж", "Ж"),
+        ("NUMBER", "½", "a"),
+        ("SPACE", "\u{a0}", "a"),
+        ("CONTROL", "\u{7}", "a")
+    ];
+    for (rule, matched, not_matched) in cases {
+        let source = format!("main := {}", rule);
+        assert!(matches(&source, matched), "{} {:?}", rule, matched);
+        assert!(!matches(&source, not_matched), "{} {:?}", rule, not_matched);
+    }
+}
+
+#[test]
+fn end_of_input() {
+    assert!(matches("main := 'a' EOI", "a"));
+    assert!(!matches("main := 'a' EOI 'b'", "ab"));
+}
+
+#[test]
+fn own_definition_first() {
+    assert!(matches("DIGIT := 'x'\nmain := DIGIT", "x"));
+    assert!(!matches("DIGIT := 'x'\nmain := DIGIT", "1"));
+}
+
+#[test]
+fn readme_example() {
+    let source = "expr := term (('+'|'-') term)*\nterm := factor (('*'|'/') factor)*\nfactor := '(' expr ')' | DIGIT+\nmain := expr";
+    assert!(matches(source, "18*(1-2)"));
+    assert!(!matches(source, "18*(1-)"));
+}
@@ -0,0 +1,31 @@
+use ray_rengine::rayRengine::Grammar;
+
+#[test]
+fn readme_example() {
+    let grammar = Grammar::new("_ws := ' '*\n@number := DIGIT+\nexpr := number (_ws ('+' | '-') _ws number)*".to_string()).unwrap();
+    let tree = grammar.parse_rule("expr", "1 + 23".to_string()).unwrap();
+    assert_eq!(tree.to_sexp("1 + 23"), r#"(expr 0 6 (number 0 1 "1") (number 4 6 "23"))"#);
+}
+
+#[test]
+fn silent_and_atomic() {
+    let grammar = Grammar::new("_ws := ' '*\n@number := digit+\ndigit := DIGIT\n_op := '+' | '-'\nexpr := number (_ws _op _ws number)*\n@_hidden := 'x' number\nmain := expr _ws shown?\nshown := _hidden".to_string()).unwrap();
+    let input = "12 + 3 - 45 x7";
+    let tree = grammar.parse(input.to_string()).unwrap();
+    assert_eq!(tree.to_sexp(input), r#"(main 0 14 (expr 0 11 (number 0 2 "12") (number 5 6 "3") (number 9 11 "45")) (shown 12 14 "x7"))"#);
+    assert_eq!(tree.captures().keys().cloned().collect::<Vec<_>>(), vec!["expr", "main", "number", "shown"]);
+}
+
+#[test]
+fn silent_entry_rule_still_has_a_tree() {
+    let grammar = Grammar::new("_main := a a\na := 'a'".to_string()).unwrap();
+    let tree = grammar.parse_rule("_main", "aa".to_string()).unwrap();
+    assert_eq!(tree.to_sexp("aa"), r#"(_main 0 2 (a 0 1 "a") (a 1 2 "a"))"#);
+}
+
+#[test]
+fn atomic_with_its_automaton() {
+    let grammar = Grammar::new("@main := a+\na := 'a'".to_string()).unwrap();
+    assert!(grammar.context.dfas.contains_key("main"));
+    assert_eq!(grammar.parse("aa".to_string()).unwrap().to_sexp("aa"), r#"(main 0 2 "aa")"#);
+}
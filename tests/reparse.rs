@@ -0,0 +1,62 @@
+mod common;
+
+use common::{Rng, grammar, input, show};
+use ray_rengine::rayRengine::{Engine, Grammar};
+
+#[test]
+fn same_as_matching_from_scratch() {
+    let mut rng = Rng(0x9e3779b97f4a7c15);
+    for _ in 0..500 {
+        let source = grammar(&mut rng);
+        let grammar = Grammar::new(source.clone()).unwrap();
+        let mut text = input(&mut rng);
+        let mut document = grammar.document(text.clone());
+        for _ in 0..10 {
+            let start = rng.below(text.len() as u64 + 1) as usize;
+            let end = start + rng.below((text.len() - start) as u64 + 1) as usize;
+            let replacement: String = input(&mut rng).chars().take(rng.below(4) as usize).collect();
+            text.replace_range(start..end, &replacement);
+            document = grammar.reparse(document, start..end, &replacement);
+            assert_eq!(document.text(), text);
+            let fresh = grammar.string_left_rule("main", text.clone());
+            assert_eq!(show(&fresh), show(&document.result), "\n{}\ntext {:?} edit {}..{} {:?}", source, text, start, end, replacement);
+        }
+    }
+}
+
+#[test]
+fn reuses_matches() {
+    let grammar = Grammar::new("main := line*\nline := word (' ' word)* NEWLINE\nword := ('a' | 'b')+ | '(' line ')'".to_string()).unwrap();
+    let text: String = (0..2000).map(|i| if i % 7 == 0 { "ab (ba b\n) b\n" } else { "ab ba b\n" }).collect();
+    let mut document = grammar.document(text);
+    assert!(document.is_match().unwrap());
+    let before = document.remembered();
+    document = grammar.reparse(document, 800..800, "a");
+    assert!(document.is_match().unwrap());
+    assert!(document.remembered() > before / 2);
+    let document = grammar.reparse(document, 0..0, "(");
+    assert!(!document.is_match().unwrap());
+}
+
+#[test]
+fn grammar_changed_in_between() {
+    let source = "main := _item*\n_item := rec '!'\nrec := '(' rec ')' | 'a'";
+    let text = "(a)!a!((a))!".repeat(20);
+
+    // _item is written out by optimise, so none of its matches should be left
+    let mut grammar = Grammar::new(source.to_string()).unwrap();
+    let document = grammar.document(text.clone());
+    grammar.optimise().unwrap();
+    let document = grammar.reparse(document, 0..0, "a!");
+    let fresh = grammar.document(format!("a!{}", text));
+    assert_eq!(document.remembered(), fresh.remembered());
+    assert_eq!(show(&document.result), show(&fresh.result));
+
+    let mut grammar = Grammar::new(source.to_string()).unwrap();
+    let document = grammar.document(text.clone());
+    grammar.set_engine(Engine::Vm).unwrap();
+    let document = grammar.reparse(document, 0..0, "a!");
+    let fresh = grammar.document(format!("a!{}", text));
+    assert_eq!(document.remembered(), fresh.remembered());
+    assert!(document.is_match().unwrap());
+}
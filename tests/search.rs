@@ -0,0 +1,73 @@
+mod common;
+
+use common::{Rng, grammar, input};
+use ray_rengine::rayRengine::{Grammar, Engine};
+use ray_rengine::prefilter;
+
+// Trying the rule at every place in the input, one after another
+fn every_place(grammar: &Grammar, input: &str) -> Result<Option<(usize, usize)>, String> {
+    let mut starts: Vec<usize> = input.char_indices().map(|(i, _)| i).collect();
+    starts.push(input.len());
+    for start in starts {
+        match grammar.string_left_rule("main", input[start..].to_string()) {
+            Ok(left) => return Ok(Some((start, input.len() - left.len()))),
+            Err(err) => if err.name != "InputError" {
+                return Err(err.to_string())
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[test]
+fn readme_example() {
+    let grammar = Grammar::new("whitespace := ' ' | '\\t' | '\\n'\nnums := '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' | '0'\nmain := 'base' whitespace* nums+".to_string()).unwrap();
+    assert_eq!(prefilter::extract(&grammar.context, "main").prefix, "base");
+    let input = "x".repeat(1000) + "bas base 12";
+    assert_eq!(grammar.search(input).unwrap(), Some((1004, 1011)));
+}
+
+#[test]
+fn required_literal() {
+    let grammar = Grammar::new("main := ALPHA* '@' ALPHA+".to_string()).unwrap();
+    let filter = prefilter::extract(&grammar.context, "main");
+    assert_eq!(filter.prefix, "");
+    assert_eq!(filter.required, "@");
+    assert_eq!(grammar.search("12 ab@cd".to_string()).unwrap(), Some((3, 8)));
+    assert_eq!(grammar.search("12 abcd".to_string()).unwrap(), None);
+}
+
+#[test]
+fn missing_definition_still_an_error() {
+    let grammar = Grammar::new("main := 'base' missing".to_string()).unwrap();
+    assert_eq!(grammar.search("xx base".to_string()).unwrap_err().name, "NoDefinitionError");
+}
+
+#[test]
+fn same_as_trying_every_place() {
+    let mut rng = Rng(0x9e3779b97f4a7c15);
+    for _ in 0..1000 {
+        let source = grammar(&mut rng);
+        let grammar = Grammar::new(source.clone()).unwrap();
+        for _ in 0..5 {
+            let text = format!("{}{}{}", input(&mut rng), input(&mut rng), input(&mut rng));
+            let found = grammar.search(text.clone()).map_err(|err| err.to_string());
+            assert_eq!(found, every_place(&grammar, &text), "\n{}\ninput {:?}", source, text);
+        }
+    }
+}
+
+#[test]
+fn large_input_without_literals() {
+    // Nothing to skip ahead to, so the rule gets tried at every one of the places
+    let input = "b".repeat(100_000);
+    for engine in [Engine::Interpreter, Engine::Vm].iter() {
+        let mut grammar = Grammar::new("main := ALPHA DIGIT".to_string()).unwrap();
+        grammar.set_engine(*engine).unwrap();
+        let started = std::time::Instant::now();
+        assert_eq!(grammar.search(input.clone()).unwrap(), None);
+        assert_eq!(grammar.search(input.clone() + "a1").unwrap(), Some((100_000, 100_002)));
+        // Copying what's left of the input for every place took seconds
+        assert!(started.elapsed() < std::time::Duration::from_secs(3), "took {:?}", started.elapsed());
+    }
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use common::{Rng, grammar, input};
+use ray_rengine::rayRengine::Grammar;
+use ray_rengine::grammarSet::GrammarSet;
+
+#[test]
+fn readme_example() {
+    let set = GrammarSet::new(vec![
+        "main := 'PING ' DIGIT+".to_string(),
+        "main := 'LOGIN ' ALPHA+".to_string(),
+        "main := ALPHA+ ':' ANY*".to_string()
+    ]).unwrap();
+    assert_eq!(set.len(), 3);
+    assert_eq!(set.matches("PING 42".to_string()).unwrap(), vec![0]);
+    assert_eq!(set.matches("LOGIN bob".to_string()).unwrap(), vec![1]);
+    assert_eq!(set.matches("bob: PING 42".to_string()).unwrap(), vec![2]);
+    assert!(set.matches("nothing".to_string()).unwrap().is_empty());
+}
+
+#[test]
+fn rules_of_one_grammar() {
+    let source = "key := ALPHA+\nnum := DIGIT+\nobject := '{' key ':' (key | num) '}'\nmain := object";
+    let set = GrammarSet::rules(source.to_string(), vec!["key", "num", "object", "main"]).unwrap();
+    assert_eq!(set.matches("{a:1}".to_string()).unwrap(), vec![2, 3]);
+    assert_eq!(set.matches("abc".to_string()).unwrap(), vec![0]);
+    let set = GrammarSet::rules(source.to_string(), vec!["key", "array"]).unwrap();
+    assert_eq!(set.matches("abc".to_string()).unwrap_err().name, "NoDefinitionError");
+}
+
+#[test]
+fn same_as_each_on_its_own() {
+    let mut rng = Rng(0x9e3779b97f4a7c15);
+    for _ in 0..300 {
+        let mut sources: Vec<String> = (0..1 + rng.below(4)).map(|_| grammar(&mut rng)).collect();
+        // The same definitions, and the same names for different ones
+        sources.push(sources[0].clone());
+        sources.push("r0 := DIGIT\nmain := r0 'z'".to_string());
+        let grammars: Vec<Grammar> = sources.iter().map(|source| Grammar::new(source.clone()).unwrap()).collect();
+        let set = GrammarSet::new(sources.clone()).unwrap();
+        for _ in 0..10 {
+            let text = input(&mut rng);
+            let mut expected = Ok(Vec::new());
+            for (i, grammar) in grammars.iter().enumerate() {
+                match grammar.is_match(text.clone()) {
+                    Ok(true) => if let Ok(matched) = &mut expected {
+                        matched.push(i)
+                    },
+                    Ok(false) => (),
+                    Err(err) => {
+                        expected = Err(err.name);
+                        break
+                    }
+                }
+            }
+            assert_eq!(expected, set.matches(text.clone()).map_err(|err| err.name), "\n{}\ninput {:?}", sources.join("\n----\n"), text);
+        }
+    }
+}
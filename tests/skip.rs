@@ -0,0 +1,29 @@
+use ray_rengine::rayRengine::{Grammar, Engine};
+
+#[test]
+fn skipping_whitespace_between_parts() {
+    let mut grammar = Grammar::new("skip := WS\nnums := DIGIT+\n~main := nums ('+' nums)*".to_string()).unwrap();
+    for engine in [Engine::Interpreter, Engine::Vm] {
+        grammar.set_engine(engine).unwrap();
+        assert!(grammar.is_match("1 + 2+3".to_string()).unwrap());
+        assert!(!grammar.is_match("  1 +  2".to_string()).unwrap());
+        assert!(!grammar.is_match("1 2".to_string()).unwrap());
+    }
+}
+
+#[test]
+fn skip_that_matches_nothing_is_rejected() {
+    for skip in ["WS*", "WS?", "' '* | ','", "''"] {
+        let err = match Grammar::new(format!("skip := {}\n~main := 'a' 'b'", skip)) {
+            Ok(_) => panic!("skip := {} was accepted", skip),
+            Err(err) => err
+        };
+        assert_eq!(err.name, "DefinitionError");
+    }
+}
+
+#[test]
+fn unused_skip_that_matches_nothing_is_fine() {
+    let grammar = Grammar::new("skip := WS*\nmain := 'a' skip 'b'".to_string()).unwrap();
+    assert!(grammar.is_match("a  b".to_string()).unwrap());
+}
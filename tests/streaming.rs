@@ -0,0 +1,75 @@
+mod common;
+
+use common::{Rng, grammar, input};
+use ray_rengine::rayRengine::{Grammar, Engine};
+use ray_rengine::matcher::Status;
+
+#[test]
+fn same_as_all_at_once() {
+    let mut rng = Rng(0x9e3779b97f4a7c15);
+    for _ in 0..1000 {
+        let source = grammar(&mut rng);
+        let mut grammar = Grammar::new(source.clone()).unwrap();
+        grammar.set_engine(Engine::Vm).unwrap();
+        for _ in 0..10 {
+            let mut text = input(&mut rng);
+            if rng.below(3) == 0 {
+                text.push('é');
+                text.push_str(&input(&mut rng));
+            };
+            let whole = grammar.is_match(text.clone()).map_err(|err| err.name);
+            // Chunks of one to four bytes, so characters get cut in half
+            let bytes = text.as_bytes();
+            let mut matcher = grammar.matcher().unwrap();
+            let mut at = 0;
+            let mut failed = None;
+            let mut rejected = false;
+            while at < bytes.len() {
+                let end = (at + 1 + rng.below(4) as usize).min(bytes.len());
+                match matcher.feed(&bytes[at..end]) {
+                    Ok(Status::Rejected) => rejected = true,
+                    Ok(Status::Matches) => assert!(!rejected),
+                    Ok(_) => (),
+                    Err(err) => {
+                        failed = Some(err.name);
+                        break
+                    }
+                }
+                at = end;
+            }
+            let streamed = match failed {
+                Some(name) => Err(name),
+                None => matcher.finish().map_err(|err| err.name)
+            };
+            assert_eq!(whole, streamed, "\n{}\ninput {:?}", source, text);
+            if rejected {
+                assert_eq!(streamed, Ok(false));
+            }
+        }
+    }
+}
+
+#[test]
+fn keeps_about_one_line() {
+    let grammar = Grammar::new("main := line*\nline := ('a' | 'b')* NEWLINE".to_string()).unwrap();
+    let mut matcher = grammar.matcher().unwrap();
+    for _ in 0..10000 {
+        assert_eq!(matcher.feed(b"abab\r").unwrap(), Status::NeedMoreInput);
+        assert!(matcher.buffered() < 16, "{}", matcher.buffered());
+        matcher.feed(b"\n").unwrap();
+    }
+    assert!(matcher.finish().unwrap());
+}
+
+#[test]
+fn rejected_and_encoding() {
+    let grammar = Grammar::new("main := line*\nline := ('a' | 'b')* NEWLINE".to_string()).unwrap();
+    let mut matcher = grammar.matcher().unwrap();
+    assert_eq!(matcher.feed(b"ab\nc").unwrap(), Status::Rejected);
+    assert!(!matcher.finish().unwrap());
+    let mut matcher = grammar.matcher().unwrap();
+    assert_eq!(matcher.feed(&[0xff]).unwrap_err().name, "EncodingError");
+    let mut matcher = grammar.matcher().unwrap();
+    matcher.feed(&"é".as_bytes()[..1]).unwrap();
+    assert_eq!(matcher.finish().unwrap_err().name, "EncodingError");
+}
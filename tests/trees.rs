@@ -0,0 +1,73 @@
+use ray_rengine::rayRengine::Grammar;
+use ray_rengine::tree::{Tree, Visitor, Visitors};
+
+const SOURCE: &str = "item := ALPHA+\nlist := '[' item (',' item)* ']'\nmain := list";
+const INPUT: &str = "[ab,c,de]";
+
+struct Events(Vec<String>);
+
+impl Visitor for Events {
+    fn enter(&mut self, tree: &Tree) {
+        self.0.push(format!("+{}", tree.rule));
+    }
+
+    fn exit(&mut self, tree: &Tree) {
+        self.0.push(format!("-{}", tree.rule));
+    }
+}
+
+#[test]
+fn find_all_and_text() {
+    let tree = Grammar::new(SOURCE.to_string()).unwrap().parse(INPUT.to_string()).unwrap();
+    let items: Vec<&str> = tree.find_all("item").iter().map(|item| item.text(INPUT)).collect();
+    assert_eq!(items, vec!["ab", "c", "de"]);
+    assert_eq!(tree.text(INPUT), INPUT);
+}
+
+#[test]
+fn walk() {
+    let tree = Grammar::new(SOURCE.to_string()).unwrap().parse(INPUT.to_string()).unwrap();
+    let mut events = Events(Vec::new());
+    tree.walk(&mut events);
+    assert_eq!(events.0.join(" "), "+main +list +item -item +item -item +item -item -list -main");
+}
+
+#[test]
+fn visitors_by_rule() {
+    // `value` is there but never matches anything in the input
+    let source = format!("{}\nvalue := DIGIT+ | list", SOURCE);
+    let tree = Grammar::new(source).unwrap().parse(INPUT.to_string()).unwrap();
+    let mut items = Vec::new();
+    let mut lists = Vec::new();
+    let mut visitors = Visitors::new();
+    visitors.on_enter("item", |tree| items.push(tree.text(INPUT)))
+        .on_exit("list", |tree| lists.push(tree.end))
+        .on_enter("missing", |tree| panic!("entered {}", tree.rule))
+        .on_exit("value", |tree| panic!("left {}", tree.rule));
+    tree.walk(&mut visitors);
+    drop(visitors);
+    assert_eq!(items, vec!["ab", "c", "de"]);
+    assert_eq!(lists, vec![INPUT.len()]);
+}
+
+#[test]
+fn cursor() {
+    let tree = Grammar::new(SOURCE.to_string()).unwrap().parse(INPUT.to_string()).unwrap();
+    let mut cursor = tree.cursor();
+    assert!(!cursor.parent());
+    assert!(!cursor.next_sibling());
+    assert!(cursor.first_child());
+    assert_eq!(cursor.node().rule, "list");
+    assert!(cursor.first_child());
+    assert_eq!(cursor.node().text(INPUT), "ab");
+    assert!(!cursor.first_child());
+    assert!(cursor.next_sibling());
+    assert!(cursor.next_sibling());
+    assert_eq!(cursor.node().text(INPUT), "de");
+    assert!(!cursor.next_sibling());
+    assert_eq!(cursor.depth(), 2);
+    assert!(cursor.parent());
+    assert!(cursor.parent());
+    assert_eq!(cursor.node().rule, "main");
+    assert!(!cursor.parent());
+}